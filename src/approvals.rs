@@ -0,0 +1,219 @@
+use std::fmt;
+use std::str::FromStr;
+use serde::{Deserialize, Serialize};
+use anyhow::anyhow;
+use serde_json::Value;
+use crate::constans::PRODUCTION_ENVIRONMENT;
+use crate::db::Db;
+use crate::models::{GamePlatform, GameVersion, SaveDataWrite};
+use crate::save_data::{apply_save_data_patch, get_current_save_count, increase_save_count, remove_value_at_pointer, set_save_count, set_value_at_pointer};
+use crate::unity_service::UnityService;
+use crate::Error;
+
+// Commands that can be configured to need a second owner's approval. The variant is stored as JSON
+// with the approval request and executed as is once it's approved.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "command", rename_all = "camelCase")]
+pub enum ApprovableCommand {
+    #[serde(rename = "copysavedata", rename_all = "camelCase")]
+    CopySaveData {
+        to_player_id: String,
+        from_player_id: String,
+        increase_save_count_by: u64,
+        skip_validation: bool,
+        #[serde(default = "default_environment")]
+        environment: String,
+    },
+    #[serde(rename = "importsavedata", rename_all = "camelCase")]
+    ImportSaveData {
+        player_id: String,
+        filename: String,
+        save_data: Value,
+        increase_save_count_by: u64,
+        skip_validation: bool,
+        environment: String,
+    },
+    #[serde(rename = "restoresavedata", rename_all = "camelCase")]
+    RestoreSaveData {
+        player_id: String,
+        snapshot_id: i64,
+        increase_save_count_by: u64,
+        skip_validation: bool,
+        environment: String,
+    },
+    #[serde(rename = "patchsavedata", rename_all = "camelCase")]
+    PatchSaveData {
+        player_id: String,
+        filename: String,
+        patch: Value,
+        increase_save_count_by: u64,
+        skip_validation: bool,
+        environment: String,
+    },
+    #[serde(rename = "setsavefield", rename_all = "camelCase")]
+    SetSaveField {
+        player_id: String,
+        json_pointer: String,
+        json_value: Value,
+        increase_save_count_by: u64,
+        skip_validation: bool,
+        environment: String,
+    },
+    #[serde(rename = "removesavefield", rename_all = "camelCase")]
+    RemoveSaveField {
+        player_id: String,
+        json_pointer: String,
+        increase_save_count_by: u64,
+        skip_validation: bool,
+        environment: String,
+    },
+    #[serde(rename = "updategameversion", rename_all = "camelCase")]
+    UpdateGameVersion {
+        version_number: String,
+        platform: String,
+        force_update: bool,
+        #[serde(default = "default_environment")]
+        environment: String,
+    },
+}
+
+// Requests stored before environments existed were all for production.
+fn default_environment() -> String {
+    PRODUCTION_ENVIRONMENT.to_string()
+}
+
+impl ApprovableCommand {
+    pub const NAMES: [&'static str; 7] = ["copysavedata", "importsavedata", "restoresavedata", "patchsavedata", "setsavefield", "removesavefield", "updategameversion"];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ApprovableCommand::CopySaveData { .. } => "copysavedata",
+            ApprovableCommand::ImportSaveData { .. } => "importsavedata",
+            ApprovableCommand::RestoreSaveData { .. } => "restoresavedata",
+            ApprovableCommand::PatchSaveData { .. } => "patchsavedata",
+            ApprovableCommand::SetSaveField { .. } => "setsavefield",
+            ApprovableCommand::RemoveSaveField { .. } => "removesavefield",
+            ApprovableCommand::UpdateGameVersion { .. } => "updategameversion",
+        }
+    }
+
+    pub fn environment(&self) -> &str {
+        match self {
+            ApprovableCommand::CopySaveData { environment, .. } => environment,
+            ApprovableCommand::ImportSaveData { environment, .. } => environment,
+            ApprovableCommand::RestoreSaveData { environment, .. } => environment,
+            ApprovableCommand::PatchSaveData { environment, .. } => environment,
+            ApprovableCommand::SetSaveField { environment, .. } => environment,
+            ApprovableCommand::RemoveSaveField { environment, .. } => environment,
+            ApprovableCommand::UpdateGameVersion { environment, .. } => environment,
+        }
+    }
+
+    // The save is read again when the command runs, so an approved request writes on top of the current save.
+    // `actor_id` is the owner who ran or approved the command, the requester is kept on the approval.
+    pub async fn execute(&self, unity_service: &UnityService, db: &Db, actor_id: u64) -> Result<String, Error> {
+        match self {
+            ApprovableCommand::CopySaveData { to_player_id, from_player_id, increase_save_count_by, skip_validation, .. } => {
+                let (old_save_data, new_save_data) = unity_service.get_copied_save_data(to_player_id, from_player_id, *increase_save_count_by).await?;
+                let write = SaveDataWrite::new(self.name(), actor_id, *skip_validation);
+                let snapshot_id = unity_service.set_save_data(to_player_id, &old_save_data, new_save_data, &write).await?;
+                Ok(format!("Save data copied to playerId: {} from playerId: {}. The old save is stored as snapshot {}.", to_player_id, from_player_id, snapshot_id))
+            },
+            ApprovableCommand::ImportSaveData { player_id, filename, save_data, increase_save_count_by, skip_validation, .. } => {
+                let previous_save_data = unity_service.find_save_data(player_id).await?.unwrap_or(Value::Null);
+                let mut save_data = save_data.clone();
+                set_save_count(&mut save_data, get_current_save_count(&previous_save_data)? + increase_save_count_by)?;
+                let write = SaveDataWrite::new(self.name(), actor_id, *skip_validation);
+                let snapshot_id = unity_service.set_save_data(player_id, &previous_save_data, save_data, &write).await?;
+                Ok(format!("Save data imported to playerId: {} from {}. The old save is stored as snapshot {}.", player_id, filename, snapshot_id))
+            },
+            ApprovableCommand::RestoreSaveData { player_id, snapshot_id, increase_save_count_by, skip_validation, .. } => {
+                let snapshot = db.get_save_snapshot_in_db(player_id, unity_service.environment(), *snapshot_id).await?
+                    .ok_or_else(|| anyhow!("Snapshot {} not found for playerId: {}", snapshot_id, player_id))?;
+                let mut save_data: Value = serde_json::from_str(&snapshot.save_data)?;
+                let previous_save_data = unity_service.find_save_data(player_id).await?.unwrap_or(Value::Null);
+                set_save_count(&mut save_data, get_current_save_count(&previous_save_data)? + increase_save_count_by)?;
+                let write = SaveDataWrite::new(self.name(), actor_id, *skip_validation);
+                let new_snapshot_id = unity_service.set_save_data(player_id, &previous_save_data, save_data, &write).await?;
+                Ok(format!("Save data restored for playerId: {} from snapshot {}. The replaced save is stored as snapshot {}.", player_id, snapshot_id, new_snapshot_id))
+            },
+            ApprovableCommand::PatchSaveData { player_id, filename, patch, increase_save_count_by, skip_validation, .. } => {
+                let previous_save_data = unity_service.get_save_data(player_id).await?;
+                let mut save_data = apply_save_data_patch(&previous_save_data, patch.clone())?;
+                increase_save_count(&mut save_data, *increase_save_count_by)?;
+                let write = SaveDataWrite::new(self.name(), actor_id, *skip_validation);
+                let snapshot_id = unity_service.set_save_data(player_id, &previous_save_data, save_data, &write).await?;
+                Ok(format!("Applied {} to playerId: {}. The old save is stored as snapshot {}.", filename, player_id, snapshot_id))
+            },
+            ApprovableCommand::SetSaveField { player_id, json_pointer, json_value, increase_save_count_by, skip_validation, .. } => {
+                let previous_save_data = unity_service.get_save_data(player_id).await?;
+                let mut save_data = previous_save_data.clone();
+                let old_value = set_value_at_pointer(&mut save_data, json_pointer, json_value.clone())?;
+                increase_save_count(&mut save_data, *increase_save_count_by)?;
+                let write = SaveDataWrite::new(self.name(), actor_id, *skip_validation);
+                let snapshot_id = unity_service.set_save_data(player_id, &previous_save_data, save_data, &write).await?;
+                let old_value = old_value.map_or("-".to_string(), |v| v.to_string());
+                Ok(format!("Save field updated for playerId: {}. Path: {}, Before: `{}`, After: `{}`. The old save is stored as snapshot {}.", player_id, json_pointer, old_value, json_value, snapshot_id))
+            },
+            ApprovableCommand::RemoveSaveField { player_id, json_pointer, increase_save_count_by, skip_validation, .. } => {
+                let previous_save_data = unity_service.get_save_data(player_id).await?;
+                let mut save_data = previous_save_data.clone();
+                let old_value = remove_value_at_pointer(&mut save_data, json_pointer)?;
+                increase_save_count(&mut save_data, *increase_save_count_by)?;
+                let write = SaveDataWrite::new(self.name(), actor_id, *skip_validation);
+                let snapshot_id = unity_service.set_save_data(player_id, &previous_save_data, save_data, &write).await?;
+                Ok(format!("Save field removed for playerId: {}. Path: {}, Before: `{}`. The old save is stored as snapshot {}.", player_id, json_pointer, old_value, snapshot_id))
+            },
+            ApprovableCommand::UpdateGameVersion { version_number, platform, force_update, .. } => {
+                let game_version = GameVersion {
+                    version_number: version_number.clone(),
+                    force_update: *force_update,
+                };
+                unity_service.update_game_version(&game_version, GamePlatform::from_str(platform)?).await?;
+                Ok(format!("Game version updated successfully. Platform: {}, Version: {}, Forced: {}", platform, version_number, force_update))
+            },
+        }
+    }
+}
+
+impl fmt::Display for ApprovableCommand {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ApprovableCommand::CopySaveData { to_player_id, from_player_id, increase_save_count_by, skip_validation, environment } => write!(
+                f,
+                "copysavedata to playerId: {} from playerId: {}, Increase save count by: {}, Skip validation: {}, Environment: {}",
+                to_player_id, from_player_id, increase_save_count_by, skip_validation, environment
+            ),
+            ApprovableCommand::ImportSaveData { player_id, filename, increase_save_count_by, skip_validation, environment, .. } => write!(
+                f,
+                "importsavedata to playerId: {} from {}, Increase save count by: {}, Skip validation: {}, Environment: {}",
+                player_id, filename, increase_save_count_by, skip_validation, environment
+            ),
+            ApprovableCommand::RestoreSaveData { player_id, snapshot_id, increase_save_count_by, skip_validation, environment } => write!(
+                f,
+                "restoresavedata playerId: {} from snapshot {}, Increase save count by: {}, Skip validation: {}, Environment: {}",
+                player_id, snapshot_id, increase_save_count_by, skip_validation, environment
+            ),
+            ApprovableCommand::PatchSaveData { player_id, filename, increase_save_count_by, skip_validation, environment, .. } => write!(
+                f,
+                "patchsavedata {} to playerId: {}, Increase save count by: {}, Skip validation: {}, Environment: {}",
+                filename, player_id, increase_save_count_by, skip_validation, environment
+            ),
+            ApprovableCommand::SetSaveField { player_id, json_pointer, json_value, increase_save_count_by, skip_validation, environment } => write!(
+                f,
+                "setsavefield playerId: {}, Path: {}, Value: `{}`, Increase save count by: {}, Skip validation: {}, Environment: {}",
+                player_id, json_pointer, json_value, increase_save_count_by, skip_validation, environment
+            ),
+            ApprovableCommand::RemoveSaveField { player_id, json_pointer, increase_save_count_by, skip_validation, environment } => write!(
+                f,
+                "removesavefield playerId: {}, Path: {}, Increase save count by: {}, Skip validation: {}, Environment: {}",
+                player_id, json_pointer, increase_save_count_by, skip_validation, environment
+            ),
+            ApprovableCommand::UpdateGameVersion { version_number, platform, force_update, environment } => write!(
+                f,
+                "updategameversion Platform: {}, Version: {}, Forced: {}, Environment: {}",
+                platform, version_number, force_update, environment
+            ),
+        }
+    }
+}
@@ -2,59 +2,96 @@ use std::collections::{HashMap, HashSet};
 use std::env;
 use tokio::sync::RwLock;
 use std::sync::Arc;
-use poise::serenity_prelude::{ChannelId, ClientBuilder, ComponentInteraction, ComponentInteractionCollector, Context as SerenityContext, CreateInteractionResponseFollowup, EditMessage, GatewayIntents, MessageFlags};
-use crate::config::{read_owners, read_subscription_types};
-use crate::constans::{DISCORD_TOKEN, GIFT_CODE_CHANNEL, GIFT_CODE_TEST_CHANNEL, INTERACTION_LISTENER_RETRY_DELAY};
+use poise::serenity_prelude::{ChannelId, ClientBuilder, ComponentInteraction, ComponentInteractionCollector, Context as SerenityContext, CreateInteractionResponseFollowup, CreateMessage, EditMessage, GatewayIntents, MessageFlags, UserId};
+use crate::approvals::ApprovableCommand;
+use crate::config::{read_approval_required_commands, read_grant_rewards_config, read_owners, read_player_summary_config, read_subscription_types, GrantRewardsConfig, PlayerSummaryConfig};
+use crate::constans::{APPROVAL_EXPIRY_MINUTES, APPROVE_BUTTON_PREFIX, DISCORD_TOKEN, GIFT_CODE_CHANNEL, GIFT_CODE_TEST_CHANNEL, INTERACTION_LISTENER_RETRY_DELAY, PRODUCTION_ENVIRONMENT, RECONCILE_INTERVAL_MINUTES, REJECT_BUTTON_PREFIX};
 use crate::db::Db;
+use crate::environments::UnityEnvironments;
 use crate::gift_code::get_gift_code_embed;
-use crate::models::GiftCodeResponse;
+use crate::models::{ApprovalStatus, GiftCodeRedemption, GiftCodeResponse};
+use crate::reconcile::{get_reconcile_report, reconcile_gift_codes};
+use crate::unity_error::{UnityError, UnityErrorKind};
 use crate::unity_service::UnityService;
 use crate::{ContextData, Error};
 use chrono::{DateTime, Utc};
 
+// Gift codes are cached by button id, with the environment they were created in so redemptions go
+// back to the same Cloud Save.
+struct CachedGiftCode {
+    environment: String,
+    gift_code: GiftCodeResponse,
+}
+
 pub struct Bot {
-    db: Db,
-    gift_codes: RwLock<HashMap<String, GiftCodeResponse>>,
+    pub db: Arc<Db>,
+    gift_codes: RwLock<HashMap<String, CachedGiftCode>>,
     pub discord_token: String,
     pub gift_code_channel_id: u64,
     pub gift_code_test_channel_id: u64,
-    unity_service: Arc<UnityService>,
+    unity_environments: Arc<UnityEnvironments>,
     pub subscription_types: HashSet<String>,
+    pub reconcile_interval_minutes: u64,
+    pub player_summary: PlayerSummaryConfig,
+    pub grant_rewards: GrantRewardsConfig,
+    pub owners: HashSet<UserId>,
+    pub approval_required_commands: HashSet<String>,
+    pub approval_expiry_minutes: u64,
 }
 
 impl Bot {
     pub fn new(db: Db) -> Result<Self, Error> {
+        let db = Arc::new(db);
         Ok(Self {
-            db,
+            db: db.clone(),
             gift_codes:RwLock::new(HashMap::new()),
             discord_token: env::var(DISCORD_TOKEN)?,
             gift_code_channel_id: env::var(GIFT_CODE_CHANNEL)?.parse::<u64>()?,
             gift_code_test_channel_id: env::var(GIFT_CODE_TEST_CHANNEL)?.parse::<u64>()?,
-            unity_service: Arc::new(UnityService::new()?),
+            unity_environments: Arc::new(UnityEnvironments::new(db)?),
             subscription_types: read_subscription_types(),
+            reconcile_interval_minutes: env::var(RECONCILE_INTERVAL_MINUTES)?.parse::<u64>()?,
+            player_summary: read_player_summary_config(),
+            grant_rewards: read_grant_rewards_config(),
+            owners: read_owners(),
+            approval_required_commands: read_approval_required_commands(),
+            approval_expiry_minutes: env::var(APPROVAL_EXPIRY_MINUTES)?.parse::<u64>()?,
         })
     }
 }
 
 impl Bot {
     pub async fn load_gift_codes(&self) -> Result<(), Error> {
-        let server_gift_codes = self.unity_service.get_all_gift_codes().await?;
-        for gift_code in server_gift_codes.results {
-            self.insert_gift_code(gift_code).await;
+        for unity_service in self.unity_environments.all() {
+            // Only production has to be reachable to start, a broken staging setup shouldn't take the bot down.
+            let server_gift_codes = match unity_service.get_all_gift_codes().await {
+                Ok(server_gift_codes) => server_gift_codes,
+                Err(e) if unity_service.environment() != PRODUCTION_ENVIRONMENT => {
+                    eprintln!("Failed to load gift codes, skipping environment: {} error: {:?}", unity_service.environment(), e);
+                    continue;
+                },
+                Err(e) => return Err(e),
+            };
+            for gift_code in server_gift_codes.results {
+                self.insert_gift_code(unity_service.environment(), gift_code).await;
+            }
         }
         Ok(())
     }
 
-    pub async fn insert_gift_code(&self, gift_code: GiftCodeResponse) {
+    pub async fn insert_gift_code(&self, environment: &str, gift_code: GiftCodeResponse) {
         let mut gift_codes_write = self.gift_codes.write().await;
-        gift_codes_write.insert(gift_code.value.button_id.clone(), gift_code.clone());
+        gift_codes_write.insert(gift_code.value.button_id.clone(), CachedGiftCode {
+            environment: environment.to_string(),
+            gift_code,
+        });
     }
 
     pub async fn run(self: Arc<Self>) -> Result<(), Error> {
         let token = self.discord_token.clone();
-        let owners = read_owners();
+        let owners = self.owners.clone();
         let intents = GatewayIntents::non_privileged();
-        let unity_service = self.unity_service.clone();
+        let unity_environments = self.unity_environments.clone();
 
         self.load_gift_codes().await?;
 
@@ -69,17 +106,43 @@ impl Bot {
                     Bot::updatesubscription(),
                     Bot::getsavedata(),
                     Bot::copysavedata(),
+                    Bot::savehistory(),
+                    Bot::restoresavedata(),
+                    Bot::diffsavedata(),
+                    Bot::setsavefield(),
+                    Bot::removesavefield(),
+                    Bot::importsavedata(),
+                    Bot::patchsavedata(),
+                    Bot::playerkeys(),
+                    Bot::playeritem(),
+                    Bot::setplayeritem(),
+                    Bot::playerfiles(),
+                    Bot::exportplayerfile(),
+                    Bot::replaceplayerfile(),
+                    Bot::playersummary(),
+                    Bot::subscription(),
+                    Bot::grant(),
+                    Bot::granthistory(),
+                    Bot::bulk(),
+                    Bot::blockuser(),
+                    Bot::unblockuser(),
+                    Bot::revokeredemption(),
+                    Bot::reconcile(),
                 ],
                 ..Default::default()
             })
             .setup(|ctx, _ready, framework| {
                 let self_clone = self.clone();
                 Box::pin(async move {
-                    tokio::spawn(self_clone.start_giftcode_button_listeners(ctx.clone()));
+                    tokio::spawn(self_clone.clone().start_giftcode_button_listeners(ctx.clone()));
+                    tokio::spawn(self_clone.clone().listen_for_approval_button_clicks(ctx.clone()));
+                    if self_clone.reconcile_interval_minutes > 0 {
+                        tokio::spawn(self_clone.start_reconcile_job(ctx.clone()));
+                    }
                     poise::builtins::register_globally(ctx, &framework.options().commands).await?;
                     Ok(ContextData { 
                         bot: self,
-                        unity_service,
+                        unity_environments,
                     })
                 })
             })
@@ -104,18 +167,45 @@ impl Bot {
         Ok(())
     }
 
+    async fn start_reconcile_job(self: Arc<Self>, ctx: SerenityContext) {
+        let interval = std::time::Duration::from_secs(self.reconcile_interval_minutes * 60);
+        println!("Reconciling gift codes every {} minutes", self.reconcile_interval_minutes);
+        loop {
+            tokio::time::sleep(interval).await;
+            for unity_service in self.unity_environments.all() {
+                let reconciliations = match reconcile_gift_codes(&unity_service, &self.db).await {
+                    Ok(reconciliations) => reconciliations,
+                    Err(e) => {
+                        eprintln!("Failed to reconcile gift codes in {}: {:?}", unity_service.environment(), e);
+                        continue;
+                    }
+                };
+
+                if reconciliations.iter().any(|r| r.has_drift()) {
+                    let report = format!("Environment: {}\n{}", unity_service.environment(), get_reconcile_report(&reconciliations));
+                    let builder = CreateMessage::default().content(report);
+                    if let Err(e) = ChannelId::new(self.gift_code_test_channel_id).send_message(&ctx, builder).await {
+                        eprintln!("Failed to send reconcile report: {:?}", e);
+                    }
+                }
+            }
+        }
+    }
+
     async fn listen_for_giftcode_button_clicks(self: Arc<Self>, ctx: SerenityContext, channel_id: u64) {
         println!("Listening for gift code button clicks on channel: {}", channel_id);
         loop {
             match Self::wait_for_interaction(&ctx, channel_id).await {
                 Some(mci) => {
                     let ctx = ctx.clone();
+                    let environment: String;
                     let gift_code_key: String;
                     {
                         let gift_codes = self.gift_codes.read().await;
                         match gift_codes.get(&mci.data.custom_id) {
-                            Some(gift_code) => {
-                                gift_code_key = gift_code.key.clone();
+                            Some(cached) => {
+                                environment = cached.environment.clone();
+                                gift_code_key = cached.gift_code.key.clone();
                             },
                             None => {
                                 println!("Gift code not found for custom_id: {}", mci.data.custom_id);
@@ -125,7 +215,7 @@ impl Bot {
                     }
                     let self_clone = self.clone();
                     tokio::spawn(async move {
-                        match self_clone.handle_interaction(ctx, &gift_code_key, mci).await {
+                        match self_clone.handle_interaction(ctx, &environment, &gift_code_key, mci).await {
                             Ok(_) => (),
                             Err(e) => eprintln!("Error handling interaction. gift_code_key: {} error: {:?}", gift_code_key, e),
                         }
@@ -139,33 +229,63 @@ impl Bot {
         }
     }
 
-    async fn handle_interaction(self: Arc<Self>, ctx: SerenityContext, gift_code_key: &String, mci: ComponentInteraction) -> Result<(), Error> {
+    async fn handle_interaction(self: Arc<Self>, ctx: SerenityContext, environment: &str, gift_code_key: &String, mci: ComponentInteraction) -> Result<(), Error> {
         mci.defer(ctx.clone()).await?;
-    
+
+        // Failures still get a reply, otherwise the user is left looking at "thinking..." forever.
+        let message = match self.redeem_gift_code(&ctx, environment, gift_code_key, &mci).await {
+            Ok(message) => message,
+            Err(e) => {
+                eprintln!("Error redeeming gift code. gift_code_key: {} error: {}", gift_code_key, e);
+                get_redemption_error_message(&e)
+            },
+        };
+
+        let builder = CreateInteractionResponseFollowup::default()
+            .content(message)
+            .flags(MessageFlags::EPHEMERAL);
+
+        mci.create_followup(ctx.clone(), builder).await?;
+
+        Ok(())
+    }
+
+    async fn redeem_gift_code(self: &Arc<Self>, ctx: &SerenityContext, environment: &str, gift_code_key: &String, mci: &ComponentInteraction) -> Result<String, Error> {
+        let unity_service = self.unity_environments.get(environment)?;
         let mut message: String;
         let mut send_code = false;
-        let mut gift_code = self.unity_service.get_gift_code(gift_code_key.clone()).await?;
+        let mut gift_code = unity_service.get_gift_code(gift_code_key.clone()).await?;
     
         let expired_at_datetime = DateTime::parse_from_rfc3339(&gift_code.expired_at)?
             .with_timezone(&Utc);
     
-        if gift_code.amount <= 0 {
+        let user_id = mci.user.id.get();
+        if self.db.is_user_blocked_in_db(user_id).await? {
+            message = "Sorry, you are not allowed to redeem gift codes.".to_string();
+        } else if gift_code.amount == 0 {
             message = "Sorry, there are no more gift codes available.".to_string();
         } else if expired_at_datetime < Utc::now() {
             message = "Sorry, this gift code has expired.".to_string();
         } else {
             send_code = true;
-            let user_id = mci.user.id.get();
-            let is_already_redeemed = self.db.is_user_redeemed_gift_code_in_db(&gift_code_key, user_id).await?;
+            let is_already_redeemed = self.db.is_user_redeemed_gift_code_in_db(gift_code_key, user_id).await?;
             if is_already_redeemed {
                 message = "Sorry, you already redeemed this gift code. Your previous code was:".to_string();
             } else {
-                let self_clone = self.clone();
-                self_clone.decrease_gift_code_amount(&gift_code_key).await?;
+                Bot::decrease_gift_code_amount(&unity_service, gift_code_key).await?;
                 gift_code.amount -= 1;
                 let mut msg = mci.message.clone();
                 msg.edit(ctx.clone(), EditMessage::new().embed(get_gift_code_embed(&gift_code))).await?;
-                self.db.redeem_gift_code_in_db( &gift_code_key, user_id).await?;
+                let redemption = GiftCodeRedemption {
+                    gift_code_key: gift_code_key.clone(),
+                    user_id,
+                    username: mci.user.name.clone(),
+                    guild_id: mci.guild_id.map(|id| id.get()),
+                    channel_id: mci.channel_id.get(),
+                    remaining_amount: gift_code.amount,
+                    redeemed_at: Utc::now().to_rfc3339(),
+                };
+                self.db.redeem_gift_code_in_db(&redemption).await?;
                 message = "Congratulations! You have redeemed the gift code.".to_string();
             }
         }
@@ -173,20 +293,128 @@ impl Bot {
         if send_code {
             message.push_str(format!("\n{}", gift_code_key).as_str());
         }
-    
+
+        Ok(message)
+    }
+
+    // Approval requests are stored in the db, so buttons keep working after a restart and are matched by
+    // their custom_id prefix rather than by channel.
+    async fn listen_for_approval_button_clicks(self: Arc<Self>, ctx: SerenityContext) {
+        println!("Listening for approval button clicks");
+        loop {
+            let interaction = ComponentInteractionCollector::new(&ctx)
+                .filter(|mci| mci.data.custom_id.starts_with(APPROVE_BUTTON_PREFIX) || mci.data.custom_id.starts_with(REJECT_BUTTON_PREFIX))
+                .timeout(std::time::Duration::from_secs(INTERACTION_LISTENER_RETRY_DELAY))
+                .await;
+
+            if let Some(mci) = interaction {
+                let ctx = ctx.clone();
+                let self_clone = self.clone();
+                tokio::spawn(async move {
+                    let custom_id = mci.data.custom_id.clone();
+                    if let Err(e) = self_clone.handle_approval_interaction(ctx, mci).await {
+                        eprintln!("Error handling approval interaction. custom_id: {} error: {:?}", custom_id, e);
+                    }
+                });
+            }
+        }
+    }
+
+    async fn handle_approval_interaction(self: Arc<Self>, ctx: SerenityContext, mci: ComponentInteraction) -> Result<(), Error> {
+        mci.defer(ctx.clone()).await?;
+
+        let (approve, approval_id) = match mci.data.custom_id.strip_prefix(APPROVE_BUTTON_PREFIX) {
+            Some(approval_id) => (true, approval_id),
+            None => (false, mci.data.custom_id.strip_prefix(REJECT_BUTTON_PREFIX).unwrap_or_default()),
+        };
+
+        // Like gift code redemptions, failures still get a reply instead of leaving "thinking..." behind.
+        let message = match self.decide_approval(&ctx, &mci, approve, approval_id).await {
+            Ok(message) => message,
+            Err(e) => {
+                eprintln!("Error deciding approval. approval_id: {} error: {:?}", approval_id, e);
+                format!("Something went wrong while deciding this request: {}", e)
+            },
+        };
+
         let builder = CreateInteractionResponseFollowup::default()
-            .content(message.clone())
+            .content(message)
             .flags(MessageFlags::EPHEMERAL);
-    
         mci.create_followup(ctx.clone(), builder).await?;
-            
         Ok(())
     }
 
-    async fn decrease_gift_code_amount(self: Arc<Self>, gift_code_key: &String) -> Result<(), Error> {
-        let mut gift_code = self.unity_service.get_gift_code(gift_code_key.clone()).await?;
+    async fn decide_approval(&self, ctx: &SerenityContext, mci: &ComponentInteraction, approve: bool, approval_id: &str) -> Result<String, Error> {
+        let approval_id = approval_id.parse::<i64>()?;
+        let user_id = mci.user.id;
+        if !self.owners.contains(&user_id) {
+            return Ok("Only owners can approve or reject requests.".to_string());
+        }
+
+        let Some(approval) = self.db.get_approval_in_db(approval_id).await? else {
+            return Ok("Approval request not found.".to_string());
+        };
+        if approval.status != ApprovalStatus::Pending {
+            return Ok(format!("This request is already {}.", approval.status));
+        }
+
+        let expires_at = DateTime::parse_from_rfc3339(&approval.expires_at)?.with_timezone(&Utc);
+        if expires_at < Utc::now() {
+            if self.db.decide_approval_in_db(approval_id, ApprovalStatus::Expired, user_id.get()).await? {
+                Self::close_approval_message(ctx, mci, "Expired.").await?;
+            }
+            return Ok("This request has expired.".to_string());
+        }
+
+        if approve && approval.requested_by == user_id.get() {
+            return Ok("A different owner must approve this request.".to_string());
+        }
+
+        let status = if approve { ApprovalStatus::Approved } else { ApprovalStatus::Rejected };
+        if !self.db.decide_approval_in_db(approval_id, status, user_id.get()).await? {
+            return Ok("This request was already decided.".to_string());
+        }
+
+        let outcome = if approve {
+            let command: ApprovableCommand = serde_json::from_str(&approval.params)?;
+            let result = match self.unity_environments.get(command.environment()) {
+                Ok(unity_service) => command.execute(&unity_service, &self.db, user_id.get()).await,
+                Err(e) => Err(e),
+            };
+            let (status, outcome) = match result {
+                Ok(result) => (ApprovalStatus::Executed, format!("Approved by <@{}>. {}", user_id, result)),
+                Err(e) => (ApprovalStatus::Failed, format!("Approved by <@{}>, but it failed: {}", user_id, e)),
+            };
+            if let Err(e) = self.db.finish_approval_in_db(approval_id, status, &outcome).await {
+                eprintln!("Failed to record approval outcome. approval_id: {} error: {:?}", approval_id, e);
+            }
+            outcome
+        } else {
+            format!("Rejected by <@{}>.", user_id)
+        };
+
+        Self::close_approval_message(ctx, mci, &outcome).await?;
+        Ok(outcome)
+    }
+
+    async fn close_approval_message(ctx: &SerenityContext, mci: &ComponentInteraction, outcome: &str) -> Result<(), Error> {
+        let mut msg = mci.message.clone();
+        let content = format!("{}\n{}", msg.content, outcome);
+        msg.edit(ctx, EditMessage::new().content(content).components(vec![])).await?;
+        Ok(())
+    }
+
+    async fn decrease_gift_code_amount(unity_service: &UnityService, gift_code_key: &str) -> Result<(), Error> {
+        let mut gift_code = unity_service.get_gift_code(gift_code_key.to_string()).await?;
         gift_code.amount -= 1;
-        self.unity_service.save_gift_code(&gift_code_key, &gift_code).await?;
+        unity_service.save_gift_code(gift_code_key, &gift_code).await?;
+        Ok(())
+    }
+
+    pub async fn increase_gift_code_amount(unity_service: &UnityService, gift_code_key: &str) -> Result<(), Error> {
+        let mut gift_code = unity_service.get_gift_code(gift_code_key.to_string()).await?;
+        gift_code.amount += 1;
+        unity_service.save_gift_code(gift_code_key, &gift_code).await?;
         Ok(())
     }
 
@@ -196,4 +424,13 @@ impl Bot {
             .timeout(std::time::Duration::from_secs(INTERACTION_LISTENER_RETRY_DELAY))
             .await
     }
-}
\ No newline at end of file
+}
+fn get_redemption_error_message(error: &Error) -> String {
+    let message = match error.downcast_ref::<UnityError>().map(|e| e.kind) {
+        Some(UnityErrorKind::NotFound) => "Sorry, this gift code no longer exists.",
+        Some(UnityErrorKind::Conflict) | Some(UnityErrorKind::RateLimited) => "Sorry, a lot of people are redeeming right now. Please try again in a moment.",
+        Some(UnityErrorKind::Server) => "Sorry, the game servers are having trouble. Please try again later.",
+        _ => "Sorry, something went wrong while redeeming this gift code. Please try again later.",
+    };
+    message.to_string()
+}
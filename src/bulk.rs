@@ -0,0 +1,189 @@
+use std::collections::HashSet;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use anyhow::anyhow;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+use crate::config::GrantRewardsConfig;
+use crate::constans::{BULK_CONCURRENCY, BULK_ROW_LIMIT};
+use crate::models::{GiftCodeReward, SaveDataWrite};
+use crate::save_data::{apply_grant, get_extended_expiry, get_subscription_expiry, increase_save_count, set_subscription_expiry};
+use crate::unity_service::UnityService;
+use crate::Error;
+
+#[derive(Clone, Copy)]
+pub enum BulkOperation {
+    ExtendSubscription,
+    Grant,
+}
+
+impl FromStr for BulkOperation {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "extendsubscription" => Ok(BulkOperation::ExtendSubscription),
+            "grant" => Ok(BulkOperation::Grant),
+            _ => Err(format!("Invalid bulk operation: {}. Use extendsubscription or grant", s).into()),
+        }
+    }
+}
+
+// Only player_id is required; the other columns override the command's parameters for that row.
+#[derive(Clone, Debug, Deserialize)]
+pub struct BulkRow {
+    pub player_id: String,
+    #[serde(default)]
+    pub product_id: Option<String>,
+    #[serde(default)]
+    pub days: Option<i32>,
+    #[serde(default)]
+    pub rewards: Option<String>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct BulkResult {
+    pub player_id: String,
+    pub success: bool,
+    pub message: String,
+}
+
+pub struct BulkJob {
+    pub operation: BulkOperation,
+    pub product_id: Option<String>,
+    pub days: Option<i32>,
+    pub rewards: Option<GiftCodeReward>,
+    pub reason: Option<String>,
+    pub increase_save_count_by: u64,
+    // Validates and reports every row without writing anything.
+    pub dry_run: bool,
+    pub write: SaveDataWrite,
+    pub subscription_types: HashSet<String>,
+    pub grant_rewards: GrantRewardsConfig,
+}
+
+pub fn parse_bulk_csv(bytes: &[u8]) -> Result<Vec<BulkRow>, Error> {
+    let mut reader = csv::ReaderBuilder::new().trim(csv::Trim::All).from_reader(bytes);
+    let mut rows = vec![];
+    let mut player_ids = HashSet::new();
+    for (index, row) in reader.deserialize::<BulkRow>().enumerate() {
+        let row = row.map_err(|e| anyhow!("Invalid CSV row {}: {}", index + 1, e))?;
+        if row.player_id.is_empty() {
+            return Err(anyhow!("Invalid CSV row {}: player_id is empty", index + 1).into());
+        }
+        // Rows run concurrently, so two rows for one player would race and one of the writes would be lost.
+        if !player_ids.insert(row.player_id.clone()) {
+            return Err(anyhow!("Invalid CSV row {}: player_id {} is listed more than once", index + 1, row.player_id).into());
+        }
+        rows.push(row);
+    }
+
+    if rows.is_empty() {
+        return Err(anyhow!("CSV has no rows").into());
+    } else if rows.len() > BULK_ROW_LIMIT {
+        return Err(anyhow!("CSV has {} rows, the limit is {}", rows.len(), BULK_ROW_LIMIT).into());
+    }
+    Ok(rows)
+}
+
+pub fn get_bulk_report(results: &[BulkResult]) -> Result<String, Error> {
+    let mut writer = csv::Writer::from_writer(vec![]);
+    for result in results {
+        writer.serialize(result)?;
+    }
+    Ok(String::from_utf8(writer.into_inner().map_err(|e| anyhow!("{}", e))?)?)
+}
+
+// Runs the job for every row with at most BULK_CONCURRENCY rows in flight. `progress` counts finished
+// rows so the caller can report it; results keep the CSV order.
+pub async fn run_bulk_job(unity_service: Arc<UnityService>, job: Arc<BulkJob>, rows: Vec<BulkRow>, progress: Arc<AtomicUsize>) -> Vec<BulkResult> {
+    let semaphore = Arc::new(Semaphore::new(BULK_CONCURRENCY));
+    let mut tasks = JoinSet::new();
+
+    for (index, row) in rows.iter().cloned().enumerate() {
+        let semaphore = semaphore.clone();
+        let unity_service = unity_service.clone();
+        let job = job.clone();
+        let progress = progress.clone();
+        tasks.spawn(async move {
+            let _permit = semaphore.acquire_owned().await;
+            let result = run_bulk_row(&unity_service, &job, &row).await;
+            progress.fetch_add(1, Ordering::Relaxed);
+            (index, result)
+        });
+    }
+
+    let mut results: Vec<Option<BulkResult>> = vec![None; rows.len()];
+    while let Some(joined) = tasks.join_next().await {
+        let Ok((index, result)) = joined else {
+            continue;
+        };
+        results[index] = Some(match result {
+            Ok(message) => BulkResult { player_id: rows[index].player_id.clone(), success: true, message },
+            Err(e) => BulkResult { player_id: rows[index].player_id.clone(), success: false, message: e.to_string() },
+        });
+    }
+
+    results.into_iter().enumerate()
+        .map(|(index, result)| result.unwrap_or_else(|| BulkResult {
+            player_id: rows[index].player_id.clone(),
+            success: false,
+            message: "Task panicked".to_string(),
+        }))
+        .collect()
+}
+
+async fn run_bulk_row(unity_service: &UnityService, job: &BulkJob, row: &BulkRow) -> Result<String, Error> {
+    let previous_save_data = unity_service.get_save_data(&row.player_id).await?;
+    let mut save_data = previous_save_data.clone();
+
+    let message = match job.operation {
+        BulkOperation::ExtendSubscription => {
+            let product_id = row.product_id.as_ref().or(job.product_id.as_ref())
+                .ok_or_else(|| anyhow!("product_id is missing"))?;
+            if !job.subscription_types.contains(product_id) {
+                return Err(anyhow!("Invalid product ID: {}", product_id).into());
+            }
+            let days = row.days.or(job.days).ok_or_else(|| anyhow!("days is missing"))?;
+
+            let expires_at = get_extended_expiry(get_subscription_expiry(&save_data, product_id)?, days);
+            set_subscription_expiry(&mut save_data, product_id, expires_at)?;
+            increase_save_count(&mut save_data, job.increase_save_count_by)?;
+            if job.dry_run {
+                validate_dry_run(unity_service, job, &save_data)?;
+                return Ok(format!("dry run, {} would expire at {}", product_id, expires_at.to_rfc3339()));
+            }
+            let snapshot_id = unity_service.set_save_data(&row.player_id, &previous_save_data, save_data, &job.write).await?;
+            format!("{} expires at {}, snapshot {}", product_id, expires_at.to_rfc3339(), snapshot_id)
+        },
+        BulkOperation::Grant => {
+            let rewards = match &row.rewards {
+                Some(rewards) => serde_json::from_str(rewards).map_err(|e| anyhow!("Invalid rewards: {}", e))?,
+                None => job.rewards.clone().ok_or_else(|| anyhow!("rewards is missing"))?,
+            };
+            let reason = job.reason.as_deref().ok_or_else(|| anyhow!("reason is missing"))?;
+
+            apply_grant(&mut save_data, &rewards, &job.grant_rewards)?;
+            increase_save_count(&mut save_data, job.increase_save_count_by)?;
+            if job.dry_run {
+                validate_dry_run(unity_service, job, &save_data)?;
+                return Ok("dry run, rewards would be granted".to_string());
+            }
+            let (snapshot_id, grant_id) = unity_service.set_granted_save_data(&row.player_id, &previous_save_data, save_data, &rewards, reason, &job.write).await?;
+            format!("grant {}, snapshot {}", grant_id, snapshot_id)
+        },
+    };
+
+    Ok(message)
+}
+
+fn validate_dry_run(unity_service: &UnityService, job: &BulkJob, save_data: &Value) -> Result<(), Error> {
+    if job.write.skip_validation {
+        Ok(())
+    } else {
+        unity_service.validate_save_data(save_data)
+    }
+}
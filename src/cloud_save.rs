@@ -0,0 +1,58 @@
+use async_trait::async_trait;
+use anyhow::anyhow;
+use serde_json::Value;
+use crate::config::{CloudSaveConfig, UnityEnvironmentConfig};
+use crate::directory_cloud_save::DirectoryCloudSave;
+use crate::http_cloud_save::HttpCloudSave;
+use crate::memory_cloud_save::MemoryCloudSave;
+use crate::models::{AccessClass, PlayerFilesPage, PlayerItem, PlayerItemsPage};
+use crate::Error;
+
+// The Cloud Save operations the bot relies on. `UnityService` builds gift codes, game versions and save
+// data on top of these, so the same commands run against Unity or against local data.
+#[async_trait]
+pub trait CloudSave: Send + Sync {
+    // `keys` is a comma separated list, like Unity's `keys` query parameter.
+    async fn get_custom_items(&self, custom_id: &str, keys: Option<&str>) -> Result<Vec<PlayerItem>, Error>;
+    async fn set_custom_item(&self, custom_id: &str, key: &str, value: Value) -> Result<(), Error>;
+    async fn delete_custom_item(&self, custom_id: &str, key: &str) -> Result<(), Error>;
+
+    // Items are ordered by key; `after` is the last key of the previous page.
+    async fn get_player_items_page(&self, player_id: &str, keys: Option<&str>, after: Option<&str>, access_class: AccessClass) -> Result<PlayerItemsPage, Error>;
+    async fn set_player_item(&self, player_id: &str, key: &str, value: Value, access_class: AccessClass) -> Result<(), Error>;
+
+    async fn get_player_files_page(&self, player_id: &str, after: Option<&str>) -> Result<PlayerFilesPage, Error>;
+    async fn download_player_file(&self, player_id: &str, key: &str) -> Result<Vec<u8>, Error>;
+    async fn upload_player_file(&self, player_id: &str, key: &str, content: Vec<u8>, content_type: &str) -> Result<(), Error>;
+    async fn delete_player_file(&self, player_id: &str, key: &str) -> Result<(), Error>;
+}
+
+pub fn create_cloud_save(environment: &UnityEnvironmentConfig) -> Result<Box<dyn CloudSave>, Error> {
+    match environment.cloud_save() {
+        CloudSaveConfig::Http => Ok(Box::new(HttpCloudSave::new(environment)?)),
+        CloudSaveConfig::Memory => Ok(Box::new(MemoryCloudSave::new())),
+        CloudSaveConfig::Directory { path } => Ok(Box::new(DirectoryCloudSave::new(&path)?)),
+    }
+}
+
+// Local backends return everything after `after` in one page, filtered by `keys` like Unity does.
+pub(crate) fn get_local_page<T>(entries: Vec<(String, T)>, keys: Option<&str>, after: Option<&str>) -> Vec<T> {
+    let keys: Option<Vec<&str>> = keys.map(|keys| keys.split(',').map(|key| key.trim()).collect());
+    let mut entries: Vec<(String, T)> = entries.into_iter()
+        .filter(|(key, _)| keys.as_ref().is_none_or(|keys| keys.contains(&key.as_str())))
+        .filter(|(key, _)| after.is_none_or(|after| key.as_str() > after))
+        .collect();
+    entries.sort_by(|a, b| a.0.cmp(&b.0));
+    entries.into_iter().map(|(_, entry)| entry).collect()
+}
+
+// Keys end up in file names for the directory backend, so they are held to Unity's key rules there and in
+// memory alike.
+pub(crate) fn validate_key(key: &str) -> Result<(), Error> {
+    let is_valid = !key.is_empty() && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+    if is_valid {
+        Ok(())
+    } else {
+        Err(anyhow!("Invalid key '{}': only letters, digits, '_' and '-' are allowed", key).into())
+    }
+}
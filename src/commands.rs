@@ -1,17 +1,29 @@
 use std::str::FromStr;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::time::{SystemTime, UNIX_EPOCH};
-use chrono::{Utc, DateTime};
+use chrono::{Duration, NaiveDate, Utc, DateTime};
 use anyhow::anyhow;
-use poise::serenity_prelude::{ButtonStyle, ChannelId, CreateActionRow, CreateAttachment, CreateButton, CreateMessage, Http, ReactionType};
+use poise::CreateReply;
+use poise::serenity_prelude::{Attachment, ButtonStyle, ChannelId, ComponentInteractionCollector, CreateActionRow, CreateAttachment, CreateButton, CreateInteractionResponse, CreateMessage, Http, ReactionType};
 use rand::Rng;
 use serde_json::Value;
+use crate::approvals::ApprovableCommand;
 use crate::bot::Bot;
+use crate::bulk::{get_bulk_report, parse_bulk_csv, run_bulk_job, BulkJob, BulkOperation};
+use crate::constans::{APPROVE_BUTTON_PREFIX, BULK_PROGRESS_INTERVAL, CONFIRMATION_TIMEOUT, GRANT_HISTORY_LIMIT, MESSAGE_LENGTH_LIMIT, PLAYER_FILE_BACKUP_SIZE_LIMIT, PRODUCTION_ENVIRONMENT, REJECT_BUTTON_PREFIX, SAVE_HISTORY_LIMIT};
 use crate::gift_code::{add_days_to_current_date, generate_gift_code, get_gift_code_embed, is_valid_gift_code, validate_gift_code};
-use crate::models::{GamePlatform, GameVersion, GiftCode, GiftCodeResponse};
+use crate::json_diff::{diff_json, get_diff_embed, DiffEntry};
+use crate::models::{AccessClass, GamePlatform, GameVersion, GiftCode, GiftCodeResponse, GiftCodeReward, SaveDataWrite, SubscriptionChange};
+use crate::player_summary::{format_subscription, get_player_summary_embed};
+use crate::reconcile::{get_reconcile_report, reconcile_gift_codes, repair_gift_code, ReconcileRepair};
+use crate::save_data::{apply_grant, apply_save_data_patch, get_current_save_count, get_extended_expiry, get_subscription_expiry, increase_save_count, remove_value_at_pointer, set_save_count, set_subscription_expiry, set_value_at_pointer, update_subscription};
+use crate::unity_service::UnityService;
 use crate::{Context, Error};
 
 
 impl Bot {
+    #[allow(clippy::too_many_arguments)]
     #[poise::command(slash_command, prefix_command, owners_only)]
     pub async fn addgiftcode(
         ctx: Context<'_>,
@@ -22,25 +34,26 @@ impl Bot {
         rewards: Value,
         test: bool,
         hidden: bool,
+        #[autocomplete = "Self::autocomplete_environment"] environment: Option<String>,
     ) -> Result<(), Error> {
-        validate_gift_code(&title, &subtitle, amount, duration, &rewards)?;
-        let unity_service = ctx.data().unity_service.clone();
-
         if ctx.channel_id() != ctx.data().bot.gift_code_test_channel_id {
             return Err(anyhow!("This command can only be used in the test channel").into());
         }
 
+        validate_gift_code(&title, &subtitle, amount, duration, &rewards)?;
+        let unity_service = Bot::get_unity_service(ctx, environment.as_deref(), !test)?;
+
         let gift_code_count = unity_service.get_gift_code_count().await?;
         if gift_code_count > 19 {
             return Err(anyhow!(format!("Gift code limit reached. Gift code count: {}", gift_code_count)).into());
         }
 
-        let channel_id: u64;
-        if test || hidden {
-            channel_id = ctx.data().bot.gift_code_test_channel_id;
+        // Gift codes outside production are only for testing, so they never go to the public channel.
+        let channel_id = if test || hidden || unity_service.environment() != PRODUCTION_ENVIRONMENT {
+            ctx.data().bot.gift_code_test_channel_id
         } else {
-            channel_id = ctx.data().bot.gift_code_channel_id;
-        }
+            ctx.data().bot.gift_code_channel_id
+        };
 
         let code = generate_gift_code();
         let expiration_date = add_days_to_current_date(duration as i64);
@@ -62,6 +75,7 @@ impl Bot {
 
         if !test {
             unity_service.save_gift_code(&code, &gift_code).await?;
+            ctx.data().bot.db.record_gift_code_in_db(&code, amount).await?;
         }
         
         let channel_id = ChannelId::new(channel_id);
@@ -87,7 +101,7 @@ impl Bot {
                     key: code.clone(),
                     value: gift_code.clone(),
                 };
-                ctx.data().bot.insert_gift_code(gift_code_response).await;
+                ctx.data().bot.insert_gift_code(unity_service.environment(), gift_code_response).await;
             }
             let response = format!("Gift code added! Title: {}, Code: {}, ExpiredAt: {} Amount: {}, Rewards: {}", title, code, expiration_date, amount, rewards);
             ctx.say(response).await?;
@@ -100,14 +114,23 @@ impl Bot {
     pub async fn removegiftcode(
         ctx: Context<'_>,
         code: String,
+        dry_run: Option<bool>,
+        #[autocomplete = "Self::autocomplete_environment"] environment: Option<String>,
     ) -> Result<(), Error> {
         if code.is_empty() {
             return Err(anyhow!("Code cannot be empty").into());
-        } else if is_valid_gift_code(&code) == false {
+        } else if !is_valid_gift_code(&code) {
             return Err(anyhow!("Invalid gift code").into());
         } 
 
-        let unity_service = ctx.data().unity_service.clone();
+        let unity_service = Bot::get_unity_service(ctx, environment.as_deref(), !dry_run.unwrap_or(false))?;
+        if dry_run.unwrap_or(false) {
+            let gift_code = unity_service.get_gift_code(code.clone()).await?;
+            let response = format!("Dry run, nothing was written to Cloud Save. Would delete gift code: {}, Title: {}, Remaining amount: {}", code, gift_code.title, gift_code.amount);
+            ctx.say(response).await?;
+            return Ok(());
+        }
+
         unity_service.delete_gift_code(&code).await?;
         
         let response = format!("Gift code deleted! Code: {}", code);
@@ -116,44 +139,159 @@ impl Bot {
     }
 
     #[poise::command(slash_command, prefix_command, owners_only)]
-    pub async fn updategameversion(ctx: Context<'_>, version_number: String, platform: String, force_update: bool) -> Result<(), Error> {
+    pub async fn updategameversion(ctx: Context<'_>, version_number: String, platform: String, force_update: bool, dry_run: Option<bool>, #[autocomplete = "Self::autocomplete_environment"] environment: Option<String>) -> Result<(), Error> {
         let game_version = GameVersion {
             version_number: version_number.clone(),
             force_update,
         };
 
         let platform_object = GamePlatform::from_str(&platform)?;
-        let unity_service = ctx.data().unity_service.clone();
-        unity_service.update_game_version(&game_version, platform_object).await?;
-        let response = format!("Game version updated successfully. Platform: {}, Version: {}, Forced: {}", platform.to_string(), version_number, force_update);
-        ctx.say(response).await?;
-        Ok(())
+        let unity_service = Bot::get_unity_service(ctx, environment.as_deref(), !dry_run.unwrap_or(false))?;
+        if dry_run.unwrap_or(false) {
+            let current = match unity_service.get_game_version(&platform_object).await? {
+                Some(current) => serde_json::to_string(&current)?,
+                None => "-".to_string(),
+            };
+            let response = format!("Dry run, nothing was written to Cloud Save. Would set game_version {} from {} to {}", platform_object, current, serde_json::to_string(&game_version)?);
+            ctx.say(response).await?;
+            return Ok(());
+        }
+
+        let command = ApprovableCommand::UpdateGameVersion {
+            version_number,
+            platform,
+            force_update,
+            environment: unity_service.environment().to_string(),
+        };
+        Bot::execute_or_request_approval(ctx, &unity_service, command).await
     } 
 
+    #[allow(clippy::too_many_arguments)]
     #[poise::command(slash_command, prefix_command, owners_only)]
-    pub async fn updatesubscription(ctx: Context<'_>, player_id: String, product_id: String, duration: i32, increase_save_count_by: u64) -> Result<(), Error> {
+    pub async fn updatesubscription(ctx: Context<'_>, player_id: String, product_id: String, duration: i32, increase_save_count_by: u64, skip_validation: Option<bool>, dry_run: Option<bool>, #[autocomplete = "Self::autocomplete_environment"] environment: Option<String>) -> Result<(), Error> {
         if ctx.channel_id() != ctx.data().bot.gift_code_test_channel_id {
             return Err(anyhow!("This command can only be used in the test channel").into());
-        } else if ctx.data().bot.subscription_types.contains(&product_id) == false {
+        } else if !ctx.data().bot.subscription_types.contains(&product_id) {
             return Err(anyhow!("Invalid product ID").into());
         } else if increase_save_count_by < 1 {
             return Err(anyhow!("Increase save count by must be greater than 0").into());
         }
         
-        let unity_service = ctx.data().unity_service.clone();
-        unity_service.update_subscription_data(&player_id, &product_id, duration, increase_save_count_by).await?;
+        let unity_service = Bot::get_unity_service(ctx, environment.as_deref(), !dry_run.unwrap_or(false))?;
+        if dry_run.unwrap_or(false) {
+            let old_save_data = unity_service.get_save_data(&player_id).await?;
+            let mut new_save_data = old_save_data.clone();
+            update_subscription(&mut new_save_data, &product_id, duration, increase_save_count_by)?;
+            return Bot::send_dry_run_diff(ctx, &unity_service, &player_id, &old_save_data, &new_save_data, skip_validation.unwrap_or(false)).await;
+        }
+
+        let write = SaveDataWrite::new(&ctx.command().qualified_name, ctx.author().id.get(), skip_validation.unwrap_or(false));
+        unity_service.update_subscription_data(&player_id, &product_id, duration, increase_save_count_by, &write).await?;
         let response = format!("Subscription updated successfully. Player ID: {}, Product ID: {}, Duration: {}", player_id, product_id, duration);
         ctx.say(response).await?;
         Ok(())
     }
 
+    #[poise::command(slash_command, prefix_command, owners_only, subcommands("Self::subscription_view", "Self::subscription_extend", "Self::subscription_set", "Self::subscription_revoke"), subcommand_required)]
+    pub async fn subscription(_ctx: Context<'_>) -> Result<(), Error> {
+        Ok(())
+    }
+
+    #[poise::command(slash_command, prefix_command, owners_only, rename = "view")]
+    pub async fn subscription_view(ctx: Context<'_>, player_id: String, #[autocomplete = "Self::autocomplete_environment"] environment: Option<String>) -> Result<(), Error> {
+        if ctx.channel_id() != ctx.data().bot.gift_code_test_channel_id {
+            return Err(anyhow!("This command can only be used in the test channel").into());
+        }
+
+        let unity_service = Bot::get_unity_service(ctx, environment.as_deref(), false)?;
+        let save_data = unity_service.get_save_data(&player_id).await?;
+        let subscriptions = save_data
+            .pointer("/playerAccountData/shopData/shopSubscriptionData")
+            .and_then(|v| v.as_object())
+            .ok_or_else(|| anyhow!("'shopSubscriptionData' not found or null"))?;
+
+        if subscriptions.is_empty() {
+            ctx.say(format!("No subscriptions found for playerId: {}", player_id)).await?;
+            return Ok(());
+        }
+
+        let mut response = format!("Subscriptions for playerId: {}\n", player_id);
+        for (product_id, product) in subscriptions {
+            response.push_str(&format!("**{}**: {}\n", product_id, format_subscription(product)));
+        }
+        ctx.say(response).await?;
+        Ok(())
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    #[poise::command(slash_command, prefix_command, owners_only, rename = "extend")]
+    pub async fn subscription_extend(ctx: Context<'_>, player_id: String, product_id: String, days: i32, increase_save_count_by: u64, skip_validation: Option<bool>, dry_run: Option<bool>, #[autocomplete = "Self::autocomplete_environment"] environment: Option<String>) -> Result<(), Error> {
+        Bot::change_subscription(ctx, &player_id, &product_id, SubscriptionChange::Extend(days), increase_save_count_by, skip_validation.unwrap_or(false), dry_run.unwrap_or(false), environment.as_deref()).await
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    #[poise::command(slash_command, prefix_command, owners_only, rename = "set")]
+    pub async fn subscription_set(ctx: Context<'_>, player_id: String, product_id: String, expires_at: String, increase_save_count_by: u64, skip_validation: Option<bool>, dry_run: Option<bool>, #[autocomplete = "Self::autocomplete_environment"] environment: Option<String>) -> Result<(), Error> {
+        let expires_at = Bot::parse_date(&expires_at)?;
+        Bot::change_subscription(ctx, &player_id, &product_id, SubscriptionChange::Set(expires_at), increase_save_count_by, skip_validation.unwrap_or(false), dry_run.unwrap_or(false), environment.as_deref()).await
+    }
+
+    #[poise::command(slash_command, prefix_command, owners_only, rename = "revoke")]
+    pub async fn subscription_revoke(ctx: Context<'_>, player_id: String, product_id: String, increase_save_count_by: u64, skip_validation: Option<bool>, dry_run: Option<bool>, #[autocomplete = "Self::autocomplete_environment"] environment: Option<String>) -> Result<(), Error> {
+        Bot::change_subscription(ctx, &player_id, &product_id, SubscriptionChange::Revoke, increase_save_count_by, skip_validation.unwrap_or(false), dry_run.unwrap_or(false), environment.as_deref()).await
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    async fn change_subscription(ctx: Context<'_>, player_id: &str, product_id: &str, change: SubscriptionChange, increase_save_count_by: u64, skip_validation: bool, dry_run: bool, environment: Option<&str>) -> Result<(), Error> {
+        if ctx.channel_id() != ctx.data().bot.gift_code_test_channel_id {
+            return Err(anyhow!("This command can only be used in the test channel").into());
+        } else if !ctx.data().bot.subscription_types.contains(product_id) {
+            return Err(anyhow!("Invalid product ID").into());
+        } else if increase_save_count_by < 1 {
+            return Err(anyhow!("Increase save count by must be greater than 0").into());
+        }
+
+        let unity_service = Bot::get_unity_service(ctx, environment, !dry_run)?;
+        let previous_save_data = unity_service.get_save_data(player_id).await?;
+        let mut save_data = previous_save_data.clone();
+        let current_expires_at = get_subscription_expiry(&save_data, product_id)?;
+
+        let now = Utc::now();
+        let (expires_at, needs_confirmation) = match change {
+            SubscriptionChange::Extend(days) => (get_extended_expiry(current_expires_at, days), days < 0),
+            SubscriptionChange::Set(expires_at) => (expires_at, true),
+            SubscriptionChange::Revoke => (now, true),
+        };
+
+        set_subscription_expiry(&mut save_data, product_id, expires_at)?;
+        increase_save_count(&mut save_data, increase_save_count_by)?;
+        if dry_run {
+            return Bot::send_dry_run_diff(ctx, &unity_service, player_id, &previous_save_data, &save_data, skip_validation).await;
+        }
+
+        let before = current_expires_at.map_or("-".to_string(), |expires_at| expires_at.to_rfc3339());
+        if needs_confirmation {
+            let prompt = format!("Change {} for playerId: {} from {} to {}?", product_id, player_id, before, expires_at.to_rfc3339());
+            if !Bot::confirm(ctx, prompt).await? {
+                return Ok(());
+            }
+            Bot::ensure_save_unchanged(&unity_service, player_id, &previous_save_data).await?;
+        }
+
+        let write = SaveDataWrite::new(&ctx.command().qualified_name, ctx.author().id.get(), skip_validation);
+        let snapshot_id = unity_service.set_save_data(player_id, &previous_save_data, save_data, &write).await?;
+
+        ctx.say(format!("Subscription updated. Player ID: {}, Product ID: {}, Before: {}, After: {}. The old save is stored as snapshot {}.", player_id, product_id, before, expires_at.to_rfc3339(), snapshot_id)).await?;
+        Ok(())
+    }
+
     #[poise::command(slash_command, prefix_command, owners_only)]
-    pub async fn getsavedata(ctx: Context<'_>, player_id: String) -> Result<(), Error> {
+    pub async fn getsavedata(ctx: Context<'_>, player_id: String, #[autocomplete = "Self::autocomplete_environment"] environment: Option<String>) -> Result<(), Error> {
         if ctx.channel_id() != ctx.data().bot.gift_code_test_channel_id {
             return Err(anyhow!("This command can only be used in the test channel").into());
         }
 
-        let unity_service = ctx.data().unity_service.clone();
+        let unity_service = Bot::get_unity_service(ctx, environment.as_deref(), false)?;
         let save_data_json = unity_service.get_save_data(&player_id).await?;
         let save_data_string = serde_json::to_string_pretty(&save_data_json)?;
         let save_data_bytes = save_data_string.as_bytes();
@@ -169,74 +307,806 @@ impl Bot {
     }
 
     #[poise::command(slash_command, prefix_command, owners_only)]
-    pub async fn copysavedata(ctx: Context<'_>, to_player_id: String, from_player_id: String, increase_save_count_by: u64) -> Result<(), Error> {
+    pub async fn playerkeys(ctx: Context<'_>, player_id: String, access_class: Option<String>, #[autocomplete = "Self::autocomplete_environment"] environment: Option<String>) -> Result<(), Error> {
         if ctx.channel_id() != ctx.data().bot.gift_code_test_channel_id {
             return Err(anyhow!("This command can only be used in the test channel").into());
+        }
+
+        let access_class = Bot::parse_access_class(access_class)?;
+        let unity_service = Bot::get_unity_service(ctx, environment.as_deref(), false)?;
+        let items = unity_service.get_all_player_items(&player_id, access_class).await?;
+        if items.is_empty() {
+            ctx.say(format!("No {} Cloud Save keys found for playerId: {}", access_class, player_id)).await?;
+            return Ok(());
+        }
+
+        let mut listing = String::new();
+        for item in &items {
+            let modified = item.modified.as_ref().map_or("-", |modified| modified.date.as_str());
+            listing.push_str(&format!("`{}` {} bytes, modified {}\n", item.key, item.size(), modified));
+        }
+
+        let title = format!("{} {} Cloud Save keys for playerId: {}", items.len(), access_class, player_id);
+        Bot::send_listing(ctx, title, listing, format!("player_keys_{}.txt", player_id)).await
+    }
+
+    #[poise::command(slash_command, prefix_command, owners_only)]
+    pub async fn playeritem(ctx: Context<'_>, player_id: String, key: String, access_class: Option<String>, #[autocomplete = "Self::autocomplete_environment"] environment: Option<String>) -> Result<(), Error> {
+        if ctx.channel_id() != ctx.data().bot.gift_code_test_channel_id {
+            return Err(anyhow!("This command can only be used in the test channel").into());
+        }
+
+        let access_class = Bot::parse_access_class(access_class)?;
+        let unity_service = Bot::get_unity_service(ctx, environment.as_deref(), false)?;
+        let item = unity_service.get_player_item(&player_id, &key, access_class).await?
+            .ok_or_else(|| anyhow!("{} key {} not found for playerId: {}", access_class, key, player_id))?;
+
+        // Values stored as JSON strings are expanded so the attachment is readable.
+        let value = match &item.value {
+            Value::String(s) => serde_json::from_str(s).unwrap_or_else(|_| item.value.clone()),
+            value => value.clone(),
+        };
+        let modified = item.modified.as_ref().map_or("-", |modified| modified.date.as_str());
+        let filename = format!("{}_{}.json", key, player_id);
+        ctx.send(CreateReply::default()
+            .content(format!("Key: {}, Access class: {}, playerId: {}, Size: {} bytes, Modified: {}", key, access_class, player_id, item.size(), modified))
+            .attachment(CreateAttachment::bytes(serde_json::to_string_pretty(&value)?.as_bytes(), filename))).await?;
+        Ok(())
+    }
+
+    #[poise::command(slash_command, prefix_command, owners_only)]
+    pub async fn setplayeritem(ctx: Context<'_>, player_id: String, key: String, value: Attachment, access_class: Option<String>, dry_run: Option<bool>, #[autocomplete = "Self::autocomplete_environment"] environment: Option<String>) -> Result<(), Error> {
+        if ctx.channel_id() != ctx.data().bot.gift_code_test_channel_id {
+            return Err(anyhow!("This command can only be used in the test channel").into());
+        }
+
+        let access_class = Bot::parse_access_class(access_class)?;
+        let unity_service = Bot::get_unity_service(ctx, environment.as_deref(), !dry_run.unwrap_or(false))?;
+        if access_class == AccessClass::Default && key == unity_service.save_data_key() {
+            return Err(anyhow!("Use importsavedata to replace the save data, so it's validated and snapshotted").into());
+        }
+
+        let new_value = Bot::read_json_attachment(&value).await?;
+        let old_value = unity_service.get_player_item(&player_id, &key, access_class).await?
+            .map_or(Value::Null, |item| item.value);
+
+        let entries = diff_json(&old_value, &new_value);
+        if dry_run.unwrap_or(false) {
+            let reply = Bot::get_diff_reply(&format!("Dry run {} {}", access_class, key), &entries, &player_id)?
+                .content(format!("Dry run, nothing was written to Cloud Save for playerId: {}", player_id));
+            ctx.send(reply).await?;
+            return Ok(());
+        }
+        ctx.send(Bot::get_diff_reply(&format!("{} {}", access_class, key), &entries, &player_id)?).await?;
+        if !Bot::confirm(ctx, format!("Write {} key {} for playerId: {}?", access_class, key, player_id)).await? {
+            return Ok(());
+        }
+
+        unity_service.set_player_item(&player_id, key.clone(), new_value, access_class).await?;
+        ctx.say(format!("{} key {} saved for playerId: {}", access_class, key, player_id)).await?;
+        Ok(())
+    }
+
+    #[poise::command(slash_command, prefix_command, owners_only)]
+    pub async fn playerfiles(ctx: Context<'_>, player_id: String, #[autocomplete = "Self::autocomplete_environment"] environment: Option<String>) -> Result<(), Error> {
+        if ctx.channel_id() != ctx.data().bot.gift_code_test_channel_id {
+            return Err(anyhow!("This command can only be used in the test channel").into());
+        }
+
+        let unity_service = Bot::get_unity_service(ctx, environment.as_deref(), false)?;
+        let files = unity_service.get_all_player_files(&player_id).await?;
+        if files.is_empty() {
+            ctx.say(format!("No Cloud Save files found for playerId: {}", player_id)).await?;
+            return Ok(());
+        }
+
+        let mut listing = String::new();
+        for file in &files {
+            let content_type = file.content_type.as_deref().unwrap_or("-");
+            let modified = file.modified.as_ref().map_or("-", |modified| modified.date.as_str());
+            listing.push_str(&format!("`{}` {} bytes, {}, modified {}\n", file.key, file.size, content_type, modified));
+        }
+
+        let title = format!("{} Cloud Save files for playerId: {}", files.len(), player_id);
+        Bot::send_listing(ctx, title, listing, format!("player_files_{}.txt", player_id)).await
+    }
+
+    #[poise::command(slash_command, prefix_command, owners_only)]
+    pub async fn exportplayerfile(ctx: Context<'_>, player_id: String, key: String, #[autocomplete = "Self::autocomplete_environment"] environment: Option<String>) -> Result<(), Error> {
+        if ctx.channel_id() != ctx.data().bot.gift_code_test_channel_id {
+            return Err(anyhow!("This command can only be used in the test channel").into());
+        }
+
+        let unity_service = Bot::get_unity_service(ctx, environment.as_deref(), false)?;
+        let content = unity_service.download_player_file(&player_id, &key).await?;
+        ctx.send(CreateReply::default()
+            .content(format!("File: {}, playerId: {}, Size: {} bytes", key, player_id, content.len()))
+            .attachment(CreateAttachment::bytes(content, format!("{}_{}", player_id, key)))).await?;
+        Ok(())
+    }
+
+    #[poise::command(slash_command, prefix_command, owners_only)]
+    pub async fn replaceplayerfile(ctx: Context<'_>, player_id: String, key: String, file: Attachment, dry_run: Option<bool>, #[autocomplete = "Self::autocomplete_environment"] environment: Option<String>) -> Result<(), Error> {
+        if ctx.channel_id() != ctx.data().bot.gift_code_test_channel_id {
+            return Err(anyhow!("This command can only be used in the test channel").into());
+        }
+
+        let unity_service = Bot::get_unity_service(ctx, environment.as_deref(), !dry_run.unwrap_or(false))?;
+        let existing = unity_service.get_all_player_files(&player_id).await?
+            .into_iter()
+            .find(|existing| existing.key == key);
+        if dry_run.unwrap_or(false) {
+            let response = match &existing {
+                Some(existing) => format!("Dry run, nothing was written to Cloud Save. Would replace file {} ({} bytes) for playerId: {} with {} ({} bytes)", key, existing.size, player_id, file.filename, file.size),
+                None => format!("Dry run, nothing was written to Cloud Save. Would create file {} for playerId: {} from {} ({} bytes)", key, player_id, file.filename, file.size),
+            };
+            ctx.say(response).await?;
+            return Ok(());
+        }
+
+        // The current file is posted first so it can be put back by hand, as files aren't snapshotted.
+        // Files too big for a Discord attachment are replaced without that copy.
+        let backed_up = match &existing {
+            Some(existing) if existing.size <= PLAYER_FILE_BACKUP_SIZE_LIMIT => {
+                let content = unity_service.download_player_file(&player_id, &key).await?;
+                ctx.send(CreateReply::default()
+                    .content(format!("Current file: {}, Size: {} bytes", key, existing.size))
+                    .attachment(CreateAttachment::bytes(content, format!("{}_{}", player_id, key)))).await?;
+                true
+            },
+            _ => false,
+        };
+
+        let prompt = match &existing {
+            Some(existing) if !backed_up => format!(
+                "Replace file {} for playerId: {} with {} ({} bytes)? The current file is {} bytes, too big to post a copy, so it can't be put back",
+                key, player_id, file.filename, file.size, existing.size
+            ),
+            Some(_) => format!("Replace file {} for playerId: {} with {} ({} bytes)?", key, player_id, file.filename, file.size),
+            None => format!("Create file {} for playerId: {} from {} ({} bytes)?", key, player_id, file.filename, file.size),
+        };
+        if !Bot::confirm(ctx, prompt).await? {
+            return Ok(());
+        }
+
+        let content_type = file.content_type.clone()
+            .or_else(|| existing.and_then(|existing| existing.content_type))
+            .unwrap_or_else(|| "application/octet-stream".to_string());
+        let content = file.download().await?;
+        unity_service.upload_player_file(&player_id, &key, content, &content_type).await?;
+
+        ctx.say(format!("File {} uploaded for playerId: {}", key, player_id)).await?;
+        Ok(())
+    }
+
+    #[poise::command(slash_command, prefix_command, owners_only)]
+    pub async fn playersummary(ctx: Context<'_>, player_id: String, #[autocomplete = "Self::autocomplete_environment"] environment: Option<String>) -> Result<(), Error> {
+        if ctx.channel_id() != ctx.data().bot.gift_code_test_channel_id {
+            return Err(anyhow!("This command can only be used in the test channel").into());
+        }
+
+        let unity_service = Bot::get_unity_service(ctx, environment.as_deref(), false)?;
+        let save_data = unity_service.get_save_data(&player_id).await?;
+        let embed = get_player_summary_embed(&player_id, &save_data, &ctx.data().bot.player_summary);
+        ctx.send(CreateReply::default().embed(embed)).await?;
+        Ok(())
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    #[poise::command(slash_command, prefix_command, owners_only)]
+    pub async fn grant(ctx: Context<'_>, player_id: String, rewards: Value, reason: String, increase_save_count_by: u64, skip_validation: Option<bool>, dry_run: Option<bool>, #[autocomplete = "Self::autocomplete_environment"] environment: Option<String>) -> Result<(), Error> {
+        if ctx.channel_id() != ctx.data().bot.gift_code_test_channel_id {
+            return Err(anyhow!("This command can only be used in the test channel").into());
+        } else if reason.trim().is_empty() {
+            return Err(anyhow!("Reason cannot be empty").into());
         } else if increase_save_count_by < 1 {
             return Err(anyhow!("Increase save count by must be greater than 0").into());
         }
 
-        let unity_service = ctx.data().unity_service.clone();
-        let old_save_data = unity_service.get_save_data(&to_player_id).await?;
-        let old_save_data_string = serde_json::to_string_pretty(&old_save_data)?;
-        let old_save_data_bytes = old_save_data_string.as_bytes();
-        let filename = format!("save_data_{}.json", to_player_id);
-        let attachment = CreateAttachment::bytes(old_save_data_bytes, filename);
+        let rewards: GiftCodeReward = serde_json::from_value(rewards)
+            .map_err(|e| anyhow!("Invalid rewards: {}", e))?;
 
-        let old_player_progress_data = old_save_data
-            .get("playerProgressData")
-            .and_then(|v| v.as_object())
-            .ok_or_else(|| anyhow!("'playerProgressData' not found or null in {}", to_player_id))?;
+        let unity_service = Bot::get_unity_service(ctx, environment.as_deref(), !dry_run.unwrap_or(false))?;
+        let old_save_data = unity_service.get_save_data(&player_id).await?;
+        let mut new_save_data = old_save_data.clone();
+        apply_grant(&mut new_save_data, &rewards, &ctx.data().bot.grant_rewards)?;
+        increase_save_count(&mut new_save_data, increase_save_count_by)?;
+        if dry_run.unwrap_or(false) {
+            return Bot::send_dry_run_diff(ctx, &unity_service, &player_id, &old_save_data, &new_save_data, skip_validation.unwrap_or(false)).await;
+        }
 
-        let old_save_count = old_player_progress_data
-            .get("saveCount")
-            .and_then(|v| v.as_u64())
-            .ok_or_else(|| anyhow!("Unable to get or cast 'saveCount' as number in {}", to_player_id))?;
+        let entries = diff_json(&old_save_data, &new_save_data);
+        ctx.send(Bot::get_diff_reply("Grant preview", &entries, &player_id)?).await?;
+        if !Bot::confirm(ctx, format!("Grant these rewards to playerId: {}?", player_id)).await? {
+            return Ok(());
+        }
+        Bot::ensure_save_unchanged(&unity_service, &player_id, &old_save_data).await?;
 
-        
-        let mut new_save_data = unity_service.get_save_data(&from_player_id).await?;
+        let write = SaveDataWrite::new(&ctx.command().qualified_name, ctx.author().id.get(), skip_validation.unwrap_or(false));
+        let (snapshot_id, grant_id) = unity_service.set_granted_save_data(&player_id, &old_save_data, new_save_data, &rewards, &reason, &write).await?;
 
-        let new_player_progress_data = new_save_data
-            .get_mut("playerProgressData")
-            .and_then(|v| v.as_object_mut())
-            .ok_or_else(|| anyhow!("'playerProgressData' not found or null in {}", from_player_id))?;
+        ctx.say(format!("Rewards granted. Player ID: {}, Grant ID: {}. The old save is stored as snapshot {}.", player_id, grant_id, snapshot_id)).await?;
+        Ok(())
+    }
 
-        new_player_progress_data.insert("saveCount".to_string(), Value::Number((old_save_count + increase_save_count_by).into()));
+    #[poise::command(slash_command, prefix_command, owners_only)]
+    pub async fn granthistory(ctx: Context<'_>, player_id: String, #[autocomplete = "Self::autocomplete_environment"] environment: Option<String>) -> Result<(), Error> {
+        if ctx.channel_id() != ctx.data().bot.gift_code_test_channel_id {
+            return Err(anyhow!("This command can only be used in the test channel").into());
+        }
 
-        unity_service.set_save_data(&to_player_id, new_save_data).await?;
+        let unity_service = Bot::get_unity_service(ctx, environment.as_deref(), false)?;
+        let grants = ctx.data().bot.db.get_grants_in_db(&player_id, unity_service.environment(), GRANT_HISTORY_LIMIT).await?;
+        if grants.is_empty() {
+            ctx.say(format!("No grants found for playerId: {}", player_id)).await?;
+            return Ok(());
+        }
 
-        let http = Http::new(&ctx.data().bot.discord_token);
+        let mut listing = String::new();
+        for grant in &grants {
+            listing.push_str(&format!("**{}** {} by <@{}>, snapshot {}: {}\n`{}`\n", grant.id, grant.created_at, grant.actor_id, grant.snapshot_id, grant.reason, grant.rewards));
+        }
+
+        let title = format!("Last {} grants for playerId: {}", grants.len(), player_id);
+        Bot::send_listing(ctx, title, listing, format!("grants_{}.txt", player_id)).await
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    #[poise::command(slash_command, prefix_command, owners_only)]
+    pub async fn bulk(
+        ctx: Context<'_>,
+        operation: String,
+        players: Attachment,
+        increase_save_count_by: u64,
+        product_id: Option<String>,
+        days: Option<i32>,
+        rewards: Option<Value>,
+        reason: Option<String>,
+        skip_validation: Option<bool>,
+        dry_run: Option<bool>,
+        #[autocomplete = "Self::autocomplete_environment"] environment: Option<String>,
+    ) -> Result<(), Error> {
+        if ctx.channel_id() != ctx.data().bot.gift_code_test_channel_id {
+            return Err(anyhow!("This command can only be used in the test channel").into());
+        } else if increase_save_count_by < 1 {
+            return Err(anyhow!("Increase save count by must be greater than 0").into());
+        }
+
+        let dry_run = dry_run.unwrap_or(false);
+        let unity_service = Bot::get_unity_service(ctx, environment.as_deref(), !dry_run)?;
+        let operation_object = BulkOperation::from_str(&operation)?;
+        if matches!(operation_object, BulkOperation::Grant) && reason.as_deref().is_none_or(|reason| reason.trim().is_empty()) {
+            return Err(anyhow!("Reason is required for grants").into());
+        }
+        let rewards = rewards
+            .map(|rewards| serde_json::from_value::<GiftCodeReward>(rewards).map_err(|e| anyhow!("Invalid rewards: {}", e)))
+            .transpose()?;
+        let rows = parse_bulk_csv(&players.download().await?)?;
+
+        if !dry_run && !Bot::confirm(ctx, format!("Run {} for {} players?", operation, rows.len())).await? {
+            return Ok(());
+        }
+
+        let row_count = rows.len();
+        let job = Arc::new(BulkJob {
+            operation: operation_object,
+            product_id,
+            days,
+            rewards,
+            reason,
+            increase_save_count_by,
+            dry_run,
+            write: SaveDataWrite::new(&ctx.command().qualified_name, ctx.author().id.get(), skip_validation.unwrap_or(false)),
+            subscription_types: ctx.data().bot.subscription_types.clone(),
+            grant_rewards: ctx.data().bot.grant_rewards.clone(),
+        });
+        let progress = Arc::new(AtomicUsize::new(0));
+        let mut task = tokio::spawn(run_bulk_job(unity_service, job, rows, progress.clone()));
+
+        // Progress goes through the interaction, whose token expires after 15 minutes. A failed update must not
+        // abandon the job, and the report is posted to the channel with the bot's own token.
+        let status = ctx.say(format!("Processed 0/{} players", row_count)).await;
+        let results = loop {
+            tokio::select! {
+                results = &mut task => break results?,
+                _ = tokio::time::sleep(std::time::Duration::from_secs(BULK_PROGRESS_INTERVAL)) => {
+                    let processed = progress.load(Ordering::Relaxed);
+                    if let Ok(status) = &status {
+                        if let Err(e) = status.edit(ctx, CreateReply::default().content(format!("Processed {}/{} players", processed, row_count))).await {
+                            eprintln!("Failed to update bulk progress: {:?}", e);
+                        }
+                    }
+                },
+            }
+        };
+
+        let failed = results.iter().filter(|result| !result.success).count();
+        if let Ok(status) = &status {
+            if let Err(e) = status.edit(ctx, CreateReply::default().content(format!("Processed {}/{} players", row_count, row_count))).await {
+                eprintln!("Failed to update bulk progress: {:?}", e);
+            }
+        }
+        let report = get_bulk_report(&results)?;
         let builder = CreateMessage::default()
-            .add_file(attachment);
-        ctx.channel_id().send_message(&http, builder).await?;
+            .content(format!("<@{}> Bulk {}{} finished. Succeeded: {}, Failed: {}", ctx.author().id, operation, if dry_run { " dry run" } else { "" }, row_count - failed, failed))
+            .add_file(CreateAttachment::bytes(report.as_bytes(), "bulk_report.csv"));
+        ctx.channel_id().send_message(ctx, builder).await?;
+        Ok(())
+    }
+
+    #[poise::command(slash_command, prefix_command, owners_only)]
+    pub async fn copysavedata(ctx: Context<'_>, to_player_id: String, from_player_id: String, increase_save_count_by: u64, skip_validation: Option<bool>, dry_run: Option<bool>, #[autocomplete = "Self::autocomplete_environment"] environment: Option<String>) -> Result<(), Error> {
+        if ctx.channel_id() != ctx.data().bot.gift_code_test_channel_id {
+            return Err(anyhow!("This command can only be used in the test channel").into());
+        } else if increase_save_count_by < 1 {
+            return Err(anyhow!("Increase save count by must be greater than 0").into());
+        }
+
+        let unity_service = Bot::get_unity_service(ctx, environment.as_deref(), !dry_run.unwrap_or(false))?;
+        if dry_run.unwrap_or(false) {
+            let (old_save_data, new_save_data) = unity_service.get_copied_save_data(&to_player_id, &from_player_id, increase_save_count_by).await?;
+            return Bot::send_dry_run_diff(ctx, &unity_service, &to_player_id, &old_save_data, &new_save_data, skip_validation.unwrap_or(false)).await;
+        }
+
+        let command = ApprovableCommand::CopySaveData {
+            to_player_id,
+            from_player_id,
+            increase_save_count_by,
+            skip_validation: skip_validation.unwrap_or(false),
+            environment: unity_service.environment().to_string(),
+        };
+        Bot::execute_or_request_approval(ctx, &unity_service, command).await
+    }
+
+    #[poise::command(slash_command, prefix_command, owners_only)]
+    pub async fn savehistory(ctx: Context<'_>, player_id: String, #[autocomplete = "Self::autocomplete_environment"] environment: Option<String>) -> Result<(), Error> {
+        if ctx.channel_id() != ctx.data().bot.gift_code_test_channel_id {
+            return Err(anyhow!("This command can only be used in the test channel").into());
+        }
+
+        let unity_service = Bot::get_unity_service(ctx, environment.as_deref(), false)?;
+        let snapshots = ctx.data().bot.db.get_save_snapshots_in_db(&player_id, unity_service.environment(), SAVE_HISTORY_LIMIT).await?;
+        if snapshots.is_empty() {
+            ctx.say(format!("No save snapshots found for playerId: {}", player_id)).await?;
+            return Ok(());
+        }
+
+        let mut response = format!("Save snapshots for playerId: {}\n", player_id);
+        for snapshot in snapshots {
+            response.push_str(&format!("**{}** {} {} by <@{}> ({} bytes)\n", snapshot.id, snapshot.created_at, snapshot.command, snapshot.actor_id, snapshot.save_data.len()));
+        }
+        ctx.say(response).await?;
+        Ok(())
+    }
+
+    #[poise::command(slash_command, prefix_command, owners_only)]
+    pub async fn restoresavedata(ctx: Context<'_>, player_id: String, snapshot_id: i64, increase_save_count_by: u64, skip_validation: Option<bool>, dry_run: Option<bool>, #[autocomplete = "Self::autocomplete_environment"] environment: Option<String>) -> Result<(), Error> {
+        if ctx.channel_id() != ctx.data().bot.gift_code_test_channel_id {
+            return Err(anyhow!("This command can only be used in the test channel").into());
+        } else if increase_save_count_by < 1 {
+            return Err(anyhow!("Increase save count by must be greater than 0").into());
+        }
+
+        let unity_service = Bot::get_unity_service(ctx, environment.as_deref(), !dry_run.unwrap_or(false))?;
+        let snapshot = ctx.data().bot.db.get_save_snapshot_in_db(&player_id, unity_service.environment(), snapshot_id).await?
+            .ok_or_else(|| anyhow!("Snapshot {} not found for playerId: {}", snapshot_id, player_id))?;
+        if dry_run.unwrap_or(false) {
+            let mut save_data: Value = serde_json::from_str(&snapshot.save_data)?;
+            let previous_save_data = unity_service.find_save_data(&player_id).await?.unwrap_or(Value::Null);
+            set_save_count(&mut save_data, get_current_save_count(&previous_save_data)? + increase_save_count_by)?;
+            return Bot::send_dry_run_diff(ctx, &unity_service, &player_id, &previous_save_data, &save_data, skip_validation.unwrap_or(false)).await;
+        }
+
+        let command = ApprovableCommand::RestoreSaveData {
+            player_id,
+            snapshot_id,
+            increase_save_count_by,
+            skip_validation: skip_validation.unwrap_or(false),
+            environment: unity_service.environment().to_string(),
+        };
+        Bot::execute_or_request_approval(ctx, &unity_service, command).await
+    }
 
-        ctx.say(format!("Save data copied to playerId: {} from playerId: {}. The old save is attached to this message.", to_player_id, from_player_id)).await?;
+    #[poise::command(slash_command, prefix_command, owners_only)]
+    pub async fn diffsavedata(ctx: Context<'_>, player_id: String, other_player_id: Option<String>, snapshot_id: Option<i64>, #[autocomplete = "Self::autocomplete_environment"] environment: Option<String>) -> Result<(), Error> {
+        if ctx.channel_id() != ctx.data().bot.gift_code_test_channel_id {
+            return Err(anyhow!("This command can only be used in the test channel").into());
+        }
+
+        let unity_service = Bot::get_unity_service(ctx, environment.as_deref(), false)?;
+        let (title, old_save_data, new_save_data) = match (other_player_id, snapshot_id) {
+            (Some(other_player_id), None) => (
+                format!("playerId: {} → playerId: {}", player_id, other_player_id),
+                unity_service.get_save_data(&player_id).await?,
+                unity_service.get_save_data(&other_player_id).await?,
+            ),
+            (None, Some(snapshot_id)) => {
+                let snapshot = ctx.data().bot.db.get_save_snapshot_in_db(&player_id, unity_service.environment(), snapshot_id).await?
+                    .ok_or_else(|| anyhow!("Snapshot {} not found for playerId: {}", snapshot_id, player_id))?;
+                (
+                    format!("playerId: {} snapshot {} → current", player_id, snapshot_id),
+                    serde_json::from_str(&snapshot.save_data)?,
+                    unity_service.get_save_data(&player_id).await?,
+                )
+            },
+            _ => return Err(anyhow!("Provide either other_player_id or snapshot_id").into()),
+        };
+
+        let entries = diff_json(&old_save_data, &new_save_data);
+        ctx.send(Bot::get_diff_reply(&title, &entries, &player_id)?).await?;
         Ok(())
     }
 
+    #[allow(clippy::too_many_arguments)]
     #[poise::command(slash_command, prefix_command, owners_only)]
-    pub async fn removestalegiftcodes(ctx: Context<'_>,) -> Result<(), Error> {
-        let unity_service = ctx.data().unity_service.clone();
+    pub async fn setsavefield(ctx: Context<'_>, player_id: String, json_pointer: String, json_value: Value, increase_save_count_by: u64, skip_validation: Option<bool>, dry_run: Option<bool>, #[autocomplete = "Self::autocomplete_environment"] environment: Option<String>) -> Result<(), Error> {
+        if ctx.channel_id() != ctx.data().bot.gift_code_test_channel_id {
+            return Err(anyhow!("This command can only be used in the test channel").into());
+        } else if increase_save_count_by < 1 {
+            return Err(anyhow!("Increase save count by must be greater than 0").into());
+        }
+
+        let unity_service = Bot::get_unity_service(ctx, environment.as_deref(), !dry_run.unwrap_or(false))?;
+        if dry_run.unwrap_or(false) {
+            let previous_save_data = unity_service.get_save_data(&player_id).await?;
+            let mut save_data = previous_save_data.clone();
+            set_value_at_pointer(&mut save_data, &json_pointer, json_value)?;
+            increase_save_count(&mut save_data, increase_save_count_by)?;
+            return Bot::send_dry_run_diff(ctx, &unity_service, &player_id, &previous_save_data, &save_data, skip_validation.unwrap_or(false)).await;
+        }
+
+        let command = ApprovableCommand::SetSaveField {
+            player_id,
+            json_pointer,
+            json_value,
+            increase_save_count_by,
+            skip_validation: skip_validation.unwrap_or(false),
+            environment: unity_service.environment().to_string(),
+        };
+        Bot::execute_or_request_approval(ctx, &unity_service, command).await
+    }
+
+    #[poise::command(slash_command, prefix_command, owners_only)]
+    pub async fn removesavefield(ctx: Context<'_>, player_id: String, json_pointer: String, increase_save_count_by: u64, skip_validation: Option<bool>, dry_run: Option<bool>, #[autocomplete = "Self::autocomplete_environment"] environment: Option<String>) -> Result<(), Error> {
+        if ctx.channel_id() != ctx.data().bot.gift_code_test_channel_id {
+            return Err(anyhow!("This command can only be used in the test channel").into());
+        } else if increase_save_count_by < 1 {
+            return Err(anyhow!("Increase save count by must be greater than 0").into());
+        }
+
+        let unity_service = Bot::get_unity_service(ctx, environment.as_deref(), !dry_run.unwrap_or(false))?;
+        if dry_run.unwrap_or(false) {
+            let previous_save_data = unity_service.get_save_data(&player_id).await?;
+            let mut save_data = previous_save_data.clone();
+            remove_value_at_pointer(&mut save_data, &json_pointer)?;
+            increase_save_count(&mut save_data, increase_save_count_by)?;
+            return Bot::send_dry_run_diff(ctx, &unity_service, &player_id, &previous_save_data, &save_data, skip_validation.unwrap_or(false)).await;
+        }
+
+        let command = ApprovableCommand::RemoveSaveField {
+            player_id,
+            json_pointer,
+            increase_save_count_by,
+            skip_validation: skip_validation.unwrap_or(false),
+            environment: unity_service.environment().to_string(),
+        };
+        Bot::execute_or_request_approval(ctx, &unity_service, command).await
+    }
+
+    #[poise::command(slash_command, prefix_command, owners_only)]
+    pub async fn importsavedata(ctx: Context<'_>, player_id: String, save_file: Attachment, increase_save_count_by: u64, skip_validation: Option<bool>, dry_run: Option<bool>, #[autocomplete = "Self::autocomplete_environment"] environment: Option<String>) -> Result<(), Error> {
+        if ctx.channel_id() != ctx.data().bot.gift_code_test_channel_id {
+            return Err(anyhow!("This command can only be used in the test channel").into());
+        } else if increase_save_count_by < 1 {
+            return Err(anyhow!("Increase save count by must be greater than 0").into());
+        }
+
+        let mut save_data = Bot::read_json_attachment(&save_file).await?;
+        if save_data.get("playerProgressData").and_then(|v| v.as_object()).is_none() {
+            return Err(anyhow!("'playerProgressData' not found or null in {}", save_file.filename).into());
+        }
+
+        let unity_service = Bot::get_unity_service(ctx, environment.as_deref(), !dry_run.unwrap_or(false))?;
+        if dry_run.unwrap_or(false) {
+            let previous_save_data = unity_service.find_save_data(&player_id).await?.unwrap_or(Value::Null);
+            set_save_count(&mut save_data, get_current_save_count(&previous_save_data)? + increase_save_count_by)?;
+            return Bot::send_dry_run_diff(ctx, &unity_service, &player_id, &previous_save_data, &save_data, skip_validation.unwrap_or(false)).await;
+        }
+
+        let command = ApprovableCommand::ImportSaveData {
+            player_id,
+            filename: save_file.filename.clone(),
+            save_data,
+            increase_save_count_by,
+            skip_validation: skip_validation.unwrap_or(false),
+            environment: unity_service.environment().to_string(),
+        };
+        Bot::execute_or_request_approval(ctx, &unity_service, command).await
+    }
+
+    #[poise::command(slash_command, prefix_command, owners_only)]
+    pub async fn patchsavedata(ctx: Context<'_>, player_id: String, patch_file: Attachment, increase_save_count_by: u64, skip_validation: Option<bool>, dry_run: Option<bool>, #[autocomplete = "Self::autocomplete_environment"] environment: Option<String>) -> Result<(), Error> {
+        if ctx.channel_id() != ctx.data().bot.gift_code_test_channel_id {
+            return Err(anyhow!("This command can only be used in the test channel").into());
+        } else if increase_save_count_by < 1 {
+            return Err(anyhow!("Increase save count by must be greater than 0").into());
+        }
+
+        let patch = Bot::read_json_attachment(&patch_file).await?;
+        let unity_service = Bot::get_unity_service(ctx, environment.as_deref(), !dry_run.unwrap_or(false))?;
+        if dry_run.unwrap_or(false) {
+            let previous_save_data = unity_service.get_save_data(&player_id).await?;
+            let mut save_data = apply_save_data_patch(&previous_save_data, patch)?;
+            increase_save_count(&mut save_data, increase_save_count_by)?;
+            return Bot::send_dry_run_diff(ctx, &unity_service, &player_id, &previous_save_data, &save_data, skip_validation.unwrap_or(false)).await;
+        }
+
+        let command = ApprovableCommand::PatchSaveData {
+            player_id,
+            filename: patch_file.filename.clone(),
+            patch,
+            increase_save_count_by,
+            skip_validation: skip_validation.unwrap_or(false),
+            environment: unity_service.environment().to_string(),
+        };
+        Bot::execute_or_request_approval(ctx, &unity_service, command).await
+    }
+
+    #[poise::command(slash_command, prefix_command, owners_only)]
+    pub async fn removestalegiftcodes(ctx: Context<'_>, dry_run: Option<bool>, #[autocomplete = "Self::autocomplete_environment"] environment: Option<String>) -> Result<(), Error> {
+        let unity_service = Bot::get_unity_service(ctx, environment.as_deref(), !dry_run.unwrap_or(false))?;
         let gift_codes = unity_service.get_all_gift_codes().await?;
         let now = Utc::now();
-        let mut removed_codes = false;
+        let mut stale_codes = vec![];
         for gift_code in gift_codes.results {
             let expired_at_datetime = DateTime::parse_from_rfc3339(&gift_code.value.expired_at)
-                .unwrap_or_else(|e| panic!("Failed to parse date: {}", e))
+                .map_err(|e| anyhow!("Invalid expiredAt for gift code {}: {}", gift_code.key, e))?
                 .with_timezone(&Utc);
 
-            if expired_at_datetime < now || gift_code.value.amount <= 0{
-                unity_service.delete_gift_code(gift_code.key.as_str()).await?;
-                ctx.say(format!("Gift code deleted! Code: {}", gift_code.key)).await?;
-                removed_codes = true;
+            if expired_at_datetime < now || gift_code.value.amount == 0 {
+                stale_codes.push(gift_code.key);
             }
         }
-        if !removed_codes {
+
+        if stale_codes.is_empty() {
             ctx.say("No stale gift codes found").await?;
+        } else if dry_run.unwrap_or(false) {
+            ctx.say(format!("Dry run, nothing was written to Cloud Save. Would delete gift codes: {}", stale_codes.join(", "))).await?;
+        } else {
+            for code in stale_codes {
+                unity_service.delete_gift_code(&code).await?;
+                ctx.say(format!("Gift code deleted! Code: {}", code)).await?;
+            }
+        }
+        Ok(())
+    }
+
+    #[poise::command(slash_command, prefix_command, owners_only)]
+    pub async fn blockuser(ctx: Context<'_>, user_id: String, reason: Option<String>, duration: Option<u32>) -> Result<(), Error> {
+        let user_id = Bot::parse_user_id(&user_id)?;
+        if duration == Some(0) {
+            return Err(anyhow!("Duration cannot be 0").into());
+        }
+
+        let expires_at = duration.map(|days| add_days_to_current_date(days as i64));
+        ctx.data().bot.db.block_user_in_db(user_id, reason.as_deref(), expires_at.as_deref(), ctx.author().id.get()).await?;
+
+        let response = format!("User blocked from redeeming gift codes. User ID: {}, Reason: {}, ExpiresAt: {}", user_id, reason.as_deref().unwrap_or("-"), expires_at.as_deref().unwrap_or("never"));
+        ctx.say(response).await?;
+        Ok(())
+    }
+
+    #[poise::command(slash_command, prefix_command, owners_only)]
+    pub async fn unblockuser(ctx: Context<'_>, user_id: String) -> Result<(), Error> {
+        let user_id = Bot::parse_user_id(&user_id)?;
+        if !ctx.data().bot.db.unblock_user_in_db(user_id).await? {
+            return Err(anyhow!("User is not blocked. User ID: {}", user_id).into());
+        }
+
+        ctx.say(format!("User unblocked. User ID: {}", user_id)).await?;
+        Ok(())
+    }
+
+    #[poise::command(slash_command, prefix_command, owners_only)]
+    pub async fn revokeredemption(ctx: Context<'_>, user_id: String, code: String, return_to_pool: bool, #[autocomplete = "Self::autocomplete_environment"] environment: Option<String>) -> Result<(), Error> {
+        let user_id = Bot::parse_user_id(&user_id)?;
+        if !is_valid_gift_code(&code) {
+            return Err(anyhow!("Invalid gift code").into());
+        }
+
+        let unity_service = Bot::get_unity_service(ctx, environment.as_deref(), return_to_pool)?;
+        let bot = ctx.data().bot.clone();
+        if !bot.db.is_user_redeemed_gift_code_in_db(&code, user_id).await? {
+            return Err(anyhow!("No redemption found. User ID: {}, Code: {}", user_id, code).into());
+        }
+
+        // Cloud Save goes first, so a failure there leaves the redemption in place to retry.
+        if return_to_pool {
+            Bot::increase_gift_code_amount(&unity_service, &code).await?;
+        }
+        bot.db.revoke_gift_code_redemption_in_db(&code, user_id, return_to_pool).await?;
+
+        let response = format!("Redemption revoked. User ID: {}, Code: {}, Returned to pool: {}", user_id, code, return_to_pool);
+        ctx.say(response).await?;
+        Ok(())
+    }
+
+    /// Compare gift code amounts with the tracked redemptions and optionally repair cloud or db
+    ///
+    /// A cloud repair skips any code that was redeemed while it ran, so a redemption is never overwritten.
+    /// Run the repair again for the skipped codes.
+    #[poise::command(slash_command, prefix_command, owners_only)]
+    pub async fn reconcile(ctx: Context<'_>, repair: Option<String>, #[autocomplete = "Self::autocomplete_environment"] environment: Option<String>) -> Result<(), Error> {
+        let repair = repair.map(|repair| ReconcileRepair::from_str(&repair)).transpose()?;
+        let unity_service = Bot::get_unity_service(ctx, environment.as_deref(), repair.is_some())?;
+        let db = &ctx.data().bot.db;
+
+        let reconciliations = reconcile_gift_codes(&unity_service, db).await?;
+        ctx.say(get_reconcile_report(&reconciliations)).await?;
+
+        if let Some(repair) = repair {
+            let mut repaired = vec![];
+            let mut skipped = vec![];
+            for reconciliation in &reconciliations {
+                let needs_repair = match repair {
+                    ReconcileRepair::Cloud => reconciliation.has_drift(),
+                    ReconcileRepair::Db => reconciliation.has_drift() || reconciliation.initial_amount.is_none(),
+                };
+                if !needs_repair {
+                    continue;
+                } else if repair_gift_code(&unity_service, db, reconciliation, &repair).await? {
+                    repaired.push(reconciliation.gift_code_key.clone());
+                } else {
+                    skipped.push(reconciliation.gift_code_key.clone());
+                }
+            }
+
+            if repaired.is_empty() && skipped.is_empty() {
+                ctx.say("Nothing to repair").await?;
+            }
+            if !repaired.is_empty() {
+                ctx.say(format!("Repaired gift codes: {}", repaired.join(", "))).await?;
+            }
+            if !skipped.is_empty() {
+                ctx.say(format!("Skipped gift codes redeemed during the repair, run it again: {}", skipped.join(", "))).await?;
+            }
+        }
+        Ok(())
+    }
+
+    async fn read_json_attachment(attachment: &Attachment) -> Result<Value, Error> {
+        let bytes = attachment.download().await?;
+        serde_json::from_slice(&bytes)
+            .map_err(|e| anyhow!("{} is not valid JSON: {}", attachment.filename, e).into())
+    }
+
+    fn get_diff_reply(title: &str, entries: &[DiffEntry], player_id: &str) -> Result<CreateReply, Error> {
+        let diff_string = serde_json::to_string_pretty(entries)?;
+        let filename = format!("save_data_diff_{}.json", player_id);
+        Ok(CreateReply::default()
+            .embed(get_diff_embed(title, entries))
+            .attachment(CreateAttachment::bytes(diff_string.as_bytes(), filename)))
+    }
+
+    // Runs the same schema validation as a real write and shows what it would change, without writing.
+    async fn send_dry_run_diff(ctx: Context<'_>, unity_service: &UnityService, player_id: &str, old_save_data: &Value, new_save_data: &Value, skip_validation: bool) -> Result<(), Error> {
+        if !skip_validation {
+            unity_service.validate_save_data(new_save_data)?;
+        }
+
+        let entries = diff_json(old_save_data, new_save_data);
+        let reply = Bot::get_diff_reply("Dry run", &entries, player_id)?
+            .content(format!("Dry run, nothing was written to Cloud Save for playerId: {}", player_id));
+        ctx.send(reply).await?;
+        Ok(())
+    }
+
+    async fn execute_or_request_approval(ctx: Context<'_>, unity_service: &UnityService, command: ApprovableCommand) -> Result<(), Error> {
+        if ctx.data().bot.approval_required_commands.contains(command.name()) {
+            return Bot::request_approval(ctx, command).await;
         }
+
+        let response = command.execute(unity_service, &ctx.data().bot.db, ctx.author().id.get()).await?;
+        ctx.say(response).await?;
         Ok(())
     }
 
+    async fn request_approval(ctx: Context<'_>, command: ApprovableCommand) -> Result<(), Error> {
+        let bot = &ctx.data().bot;
+        let expires_at = Utc::now() + Duration::minutes(bot.approval_expiry_minutes as i64);
+        let approval_id = bot.db.insert_approval_in_db(command.name(), &serde_json::to_value(&command)?, ctx.author().id.get(), ctx.channel_id().get(), &expires_at.to_rfc3339()).await?;
+
+        let reply = CreateReply::default()
+            .content(format!("Approval request {} by <@{}>: {}\nAnother owner must approve it before {}.", approval_id, ctx.author().id, command, expires_at.to_rfc3339()))
+            .components(vec![CreateActionRow::Buttons(vec![
+                CreateButton::new(format!("{}{}", APPROVE_BUTTON_PREFIX, approval_id)).style(ButtonStyle::Success).label("Approve"),
+                CreateButton::new(format!("{}{}", REJECT_BUTTON_PREFIX, approval_id)).style(ButtonStyle::Danger).label("Reject"),
+            ])]);
+        ctx.send(reply).await?;
+        Ok(())
+    }
+
+    async fn confirm(ctx: Context<'_>, prompt: String) -> Result<bool, Error> {
+        let confirm_id = format!("{}_confirm", ctx.id());
+        let cancel_id = format!("{}_cancel", ctx.id());
+        let reply = CreateReply::default()
+            .content(prompt.clone())
+            .components(vec![CreateActionRow::Buttons(vec![
+                CreateButton::new(&confirm_id).style(ButtonStyle::Danger).label("Confirm"),
+                CreateButton::new(&cancel_id).style(ButtonStyle::Secondary).label("Cancel"),
+            ])]);
+        let handle = ctx.send(reply).await?;
+
+        let button_ids = [confirm_id.clone(), cancel_id];
+        let interaction = ComponentInteractionCollector::new(ctx.serenity_context())
+            .author_id(ctx.author().id)
+            .channel_id(ctx.channel_id())
+            .filter(move |mci| button_ids.contains(&mci.data.custom_id))
+            .timeout(std::time::Duration::from_secs(CONFIRMATION_TIMEOUT))
+            .await;
+
+        let confirmed = match interaction {
+            Some(mci) => {
+                mci.create_response(ctx, CreateInteractionResponse::Acknowledge).await?;
+                mci.data.custom_id == confirm_id
+            },
+            None => false,
+        };
+
+        let outcome = if confirmed { "Confirmed" } else { "Cancelled" };
+        handle.edit(ctx, CreateReply::default().content(format!("{}\n{}", prompt, outcome)).components(vec![])).await?;
+        Ok(confirmed)
+    }
+
+    // The game can save while an owner is deciding on a confirmation. Writing the copy read before the prompt
+    // would drop that progress, so the write is called off if the save moved in the meantime.
+    async fn ensure_save_unchanged(unity_service: &UnityService, player_id: &str, save_data: &Value) -> Result<(), Error> {
+        if unity_service.get_save_data(player_id).await? != *save_data {
+            return Err(anyhow!("The save of playerId: {} changed while waiting for confirmation, nothing was written. Run the command again", player_id).into());
+        }
+        Ok(())
+    }
+
+    // Sends the listing as a message, or as a text file when it doesn't fit in one.
+    async fn send_listing(ctx: Context<'_>, title: String, listing: String, filename: String) -> Result<(), Error> {
+        if title.len() + listing.len() < MESSAGE_LENGTH_LIMIT {
+            ctx.say(format!("{}\n{}", title, listing)).await?;
+        } else {
+            ctx.send(CreateReply::default()
+                .content(title)
+                .attachment(CreateAttachment::bytes(listing.replace('`', "").replace("**", "").as_bytes(), filename))).await?;
+        }
+        Ok(())
+    }
+
+    fn get_unity_service(ctx: Context<'_>, environment: Option<&str>, write: bool) -> Result<Arc<UnityService>, Error> {
+        ctx.data().unity_environments.select(environment, write)
+    }
+
+    async fn autocomplete_environment(ctx: Context<'_>, partial: &str) -> Vec<String> {
+        ctx.data().unity_environments.names().into_iter()
+            .filter(|name| name.starts_with(partial))
+            .collect()
+    }
+
+    fn parse_access_class(access_class: Option<String>) -> Result<AccessClass, Error> {
+        access_class.map_or(Ok(AccessClass::Default), |access_class| AccessClass::from_str(&access_class))
+    }
+
+    fn parse_date(date: &str) -> Result<DateTime<Utc>, Error> {
+        if let Ok(date_time) = DateTime::parse_from_rfc3339(date) {
+            return Ok(date_time.with_timezone(&Utc));
+        }
+        let date = NaiveDate::parse_from_str(date, "%Y-%m-%d")
+            .map_err(|_| anyhow!("Invalid date: {}. Use YYYY-MM-DD or RFC 3339", date))?;
+        Ok(date.and_hms_opt(0, 0, 0).unwrap().and_utc())
+    }
+
+    fn parse_user_id(user_id: &str) -> Result<u64, Error> {
+        let trimmed = user_id.trim().trim_start_matches("<@").trim_start_matches('!').trim_end_matches('>');
+        trimmed.parse::<u64>().map_err(|_| anyhow!("Invalid user ID: {}", user_id).into())
+    }
+
     fn generate_custom_id() -> String {
         let start = SystemTime::now();
         let since_the_epoch = start
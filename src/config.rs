@@ -1,31 +1,215 @@
+use anyhow::anyhow;
 use poise::serenity_prelude::UserId;
 use serde::{Deserialize, Serialize};
-use std::collections::HashSet;
+use std::collections::{BTreeMap, HashSet};
 use std::env;
 use std::fs::File as SyncFile;
 use std::io::Read as SyncRead;
 
-use crate::constans::{BOT_USER_ID, DISCORD_BOT_CONFIG_PATH, DISCORD_TOKEN, GIFT_CODE_CHANNEL, GIFT_CODE_TEST_CHANNEL, OWNERS, SQLITE_DATABASE_PATH, SUBSCRIPTION_TYPES, UNITY_ENVIRONMENT_ID, UNITY_KEY_ID, UNITY_PROJECT_ID, UNITY_SAVE_DATA_KEY, UNITY_SECRET_KEY};
+use crate::approvals::ApprovableCommand;
+use crate::constans::{APPROVAL_EXPIRY_MINUTES, APPROVAL_REQUIRED_COMMANDS, BOT_USER_ID, DATABASE_URL, DEFAULT_UNITY_API_BASE_URL, DISCORD_BOT_CONFIG_PATH, DISCORD_TOKEN, GIFT_CODE_CHANNEL, GRANT_REWARDS, GIFT_CODE_TEST_CHANNEL, OWNERS, PLAYER_SUMMARY, PRODUCTION_ENVIRONMENT, RECONCILE_INTERVAL_MINUTES, SAVE_DATA_SCHEMA_PATH, SUBSCRIPTION_TYPES, UNITY_API_BASE_URL, UNITY_ENVIRONMENTS, UNITY_REQUEST, UNITY_SAVE_DATA_KEY};
+use crate::Error;
 
 #[derive(Debug, Deserialize, Serialize)]
 struct Config {
-    sqlite_database_path: String,
-    unity_project_id: String,
-    unity_environment_id: String,
-    unity_key_id: String,
-    unity_secret_key: String,
+    #[serde(default)]
+    sqlite_database_path: Option<String>,
+    #[serde(default)]
+    database_url: Option<String>,
+    #[serde(flatten)]
+    unity_environment: UnityEnvironmentConfig,
+    #[serde(default)]
+    environments: BTreeMap<String, UnityEnvironmentConfig>,
     unity_save_data_key: String,
+    #[serde(default)]
+    unity_api_base_url: Option<String>,
+    #[serde(default)]
+    unity_request: UnityRequestConfig,
     discord_token: String,
     owners: Vec<u64>,
     gift_code_channel: u64,
     gift_code_test_channel: u64,
     bot_user_id: u64,
     subscription_types: Vec<String>,
+    #[serde(default)]
+    reconcile_interval_minutes: u64,
+    #[serde(default)]
+    save_data_schema_path: Option<String>,
+    #[serde(default)]
+    player_summary: PlayerSummaryConfig,
+    #[serde(default)]
+    grant_rewards: GrantRewardsConfig,
+    #[serde(default)]
+    approval_required_commands: Vec<String>,
+    #[serde(default = "default_approval_expiry_minutes")]
+    approval_expiry_minutes: u64,
+}
+
+fn default_approval_expiry_minutes() -> u64 {
+    60
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct PlayerSummaryField {
+    pub label: String,
+    pub pointer: String,
+}
+
+// Maps save data JSON pointers to the fields shown by /playersummary, so it can follow the game's save format.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub struct PlayerSummaryConfig {
+    pub fields: Vec<PlayerSummaryField>,
+    pub subscriptions_pointer: String,
+}
+
+impl Default for PlayerSummaryConfig {
+    fn default() -> Self {
+        Self {
+            fields: vec![
+                PlayerSummaryField {
+                    label: "Save Count".to_string(),
+                    pointer: "/playerProgressData/saveCount".to_string(),
+                },
+                PlayerSummaryField {
+                    label: "Level".to_string(),
+                    pointer: "/playerProgressData/level".to_string(),
+                },
+                PlayerSummaryField {
+                    label: "XP".to_string(),
+                    pointer: "/playerProgressData/xp".to_string(),
+                },
+                PlayerSummaryField {
+                    label: "Currencies".to_string(),
+                    pointer: "/playerInventoryData/currencies".to_string(),
+                },
+            ],
+            subscriptions_pointer: "/playerAccountData/shopData/shopSubscriptionData".to_string(),
+        }
+    }
+}
+
+// Maps GiftCodeReward fields to the save data JSON pointers /grant writes to. `{currencyType}` in the
+// currency pointer is replaced with the reward's currency type.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub struct GrantRewardsConfig {
+    pub currency_pointer: String,
+    pub items_pointer: String,
+    pub xp_pointer: String,
+}
+
+impl Default for GrantRewardsConfig {
+    fn default() -> Self {
+        Self {
+            currency_pointer: "/playerInventoryData/currencies/{currencyType}".to_string(),
+            items_pointer: "/playerInventoryData/items".to_string(),
+            xp_pointer: "/playerProgressData/xp".to_string(),
+        }
+    }
+}
+
+// Timeouts, retries and the client-side rate limit for Unity API calls. The rate limit should match the
+// limit Unity publishes for the services the bot calls.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub struct UnityRequestConfig {
+    pub timeout_seconds: u64,
+    pub max_retries: u32,
+    pub initial_backoff_millis: u64,
+    pub max_backoff_millis: u64,
+    pub rate_limit_requests: u32,
+    pub rate_limit_window_seconds: u64,
+}
+
+impl Default for UnityRequestConfig {
+    fn default() -> Self {
+        Self {
+            timeout_seconds: 30,
+            max_retries: 3,
+            initial_backoff_millis: 500,
+            max_backoff_millis: 10_000,
+            rate_limit_requests: 10,
+            rate_limit_window_seconds: 1,
+        }
+    }
+}
+
+// Where Cloud Save data lives. `http` is Unity itself and needs the Unity credentials; `memory` and
+// `directory` are for running the bot offline.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(tag = "backend", rename_all = "snake_case")]
+pub enum CloudSaveConfig {
+    #[default]
+    Http,
+    Memory,
+    Directory { path: String },
+}
+
+// One Unity environment the bot can work on. Fields left out of an entry in `environments` are taken
+// from the top level of the config, so environments of the same project only need their own IDs.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct UnityEnvironmentConfig {
+    #[serde(default)]
+    pub unity_project_id: Option<String>,
+    #[serde(default)]
+    pub unity_environment_id: Option<String>,
+    #[serde(default)]
+    pub unity_key_id: Option<String>,
+    #[serde(default)]
+    pub unity_secret_key: Option<String>,
+    #[serde(default)]
+    pub cloud_save: Option<CloudSaveConfig>,
+}
+
+impl UnityEnvironmentConfig {
+    pub fn cloud_save(&self) -> CloudSaveConfig {
+        self.cloud_save.clone().unwrap_or_default()
+    }
+
+    fn or(self, defaults: &UnityEnvironmentConfig) -> Self {
+        Self {
+            unity_project_id: self.unity_project_id.or_else(|| defaults.unity_project_id.clone()),
+            unity_environment_id: self.unity_environment_id.or_else(|| defaults.unity_environment_id.clone()),
+            unity_key_id: self.unity_key_id.or_else(|| defaults.unity_key_id.clone()),
+            unity_secret_key: self.unity_secret_key.or_else(|| defaults.unity_secret_key.clone()),
+            cloud_save: self.cloud_save.or_else(|| defaults.cloud_save.clone()),
+        }
+    }
+}
+
+// Without an `environments` section the top level Unity settings are the production environment, as
+// before environments existed.
+pub fn get_unity_environments(environments: BTreeMap<String, UnityEnvironmentConfig>, defaults: UnityEnvironmentConfig) -> Result<BTreeMap<String, UnityEnvironmentConfig>, Error> {
+    if environments.is_empty() {
+        return Ok(BTreeMap::from([(PRODUCTION_ENVIRONMENT.to_string(), defaults)]));
+    }
+    if !environments.contains_key(PRODUCTION_ENVIRONMENT) {
+        return Err(anyhow!("environments must contain a {} environment", PRODUCTION_ENVIRONMENT).into());
+    }
+
+    let environments: BTreeMap<String, UnityEnvironmentConfig> = environments.into_iter()
+        .map(|(name, environment)| (name, environment.or(&defaults)))
+        .collect();
+    for (name, environment) in &environments {
+        if let CloudSaveConfig::Http = environment.cloud_save() {
+            let credentials = [
+                ("unity_project_id", &environment.unity_project_id),
+                ("unity_environment_id", &environment.unity_environment_id),
+                ("unity_key_id", &environment.unity_key_id),
+                ("unity_secret_key", &environment.unity_secret_key),
+            ];
+            if let Some((credential, _)) = credentials.iter().find(|(_, value)| value.is_none()) {
+                return Err(anyhow!("{} must be set for the {} environment, it uses the http cloud save backend", credential, name).into());
+            }
+        }
+    }
+    Ok(environments)
 }
 
 pub fn load_config() {
     let file_path = DISCORD_BOT_CONFIG_PATH;
-    let mut file = match SyncFile::open(&file_path) {
+    let mut file = match SyncFile::open(file_path) {
         Ok(file) => file,
         Err(e) => {
             panic!("Failed to open file {}: {}", file_path, e);
@@ -49,14 +233,31 @@ pub fn load_config() {
     env::set_var(OWNERS, owners);
     env::set_var(GIFT_CODE_CHANNEL, config.gift_code_channel.to_string());
     env::set_var(GIFT_CODE_TEST_CHANNEL, config.gift_code_test_channel.to_string());
-    env::set_var(UNITY_KEY_ID, config.unity_key_id);
-    env::set_var(UNITY_SECRET_KEY, config.unity_secret_key);
-    env::set_var(UNITY_PROJECT_ID, config.unity_project_id);
-    env::set_var(UNITY_ENVIRONMENT_ID, config.unity_environment_id);
+    let environments = get_unity_environments(config.environments, config.unity_environment)
+        .unwrap_or_else(|e| panic!("{}", e));
+    env::set_var(UNITY_ENVIRONMENTS, serde_json::to_string(&environments).expect("Failed to serialize environments"));
+    env::set_var(UNITY_API_BASE_URL, config.unity_api_base_url.unwrap_or_else(|| DEFAULT_UNITY_API_BASE_URL.to_string()));
+    env::set_var(UNITY_REQUEST, serde_json::to_string(&config.unity_request).expect("Failed to serialize unity_request"));
     env::set_var(BOT_USER_ID, config.bot_user_id.to_string());
-    env::set_var(SQLITE_DATABASE_PATH, config.sqlite_database_path);
+    let database_url = match (config.database_url, config.sqlite_database_path) {
+        (Some(database_url), _) => database_url,
+        (None, Some(sqlite_database_path)) => format!("sqlite://{}", sqlite_database_path),
+        (None, None) => panic!("Either database_url or sqlite_database_path must be set"),
+    };
+    env::set_var(DATABASE_URL, database_url);
     env::set_var(UNITY_SAVE_DATA_KEY, config.unity_save_data_key);
     env::set_var(SUBSCRIPTION_TYPES, config.subscription_types.join(","));
+    env::set_var(RECONCILE_INTERVAL_MINUTES, config.reconcile_interval_minutes.to_string());
+    env::set_var(SAVE_DATA_SCHEMA_PATH, config.save_data_schema_path.unwrap_or_default());
+    env::set_var(PLAYER_SUMMARY, serde_json::to_string(&config.player_summary).expect("Failed to serialize player_summary"));
+    env::set_var(GRANT_REWARDS, serde_json::to_string(&config.grant_rewards).expect("Failed to serialize grant_rewards"));
+    for command in &config.approval_required_commands {
+        if !ApprovableCommand::NAMES.contains(&command.as_str()) {
+            panic!("{} can't require approval. Supported commands: {}", command, ApprovableCommand::NAMES.join(", "));
+        }
+    }
+    env::set_var(APPROVAL_REQUIRED_COMMANDS, config.approval_required_commands.join(","));
+    env::set_var(APPROVAL_EXPIRY_MINUTES, config.approval_expiry_minutes.to_string());
 }
 
 pub fn read_owners() -> HashSet<UserId> {
@@ -71,4 +272,32 @@ pub fn read_subscription_types() -> HashSet<String> {
     subscription_types_str.split(',')
         .map(|s| s.to_string())
         .collect()
-}
\ No newline at end of file
+}
+
+pub fn read_player_summary_config() -> PlayerSummaryConfig {
+    let player_summary_str = env::var(PLAYER_SUMMARY).expect("PLAYER_SUMMARY not set");
+    serde_json::from_str(&player_summary_str).expect("Failed to parse PLAYER_SUMMARY")
+}
+
+pub fn read_grant_rewards_config() -> GrantRewardsConfig {
+    let grant_rewards_str = env::var(GRANT_REWARDS).expect("GRANT_REWARDS not set");
+    serde_json::from_str(&grant_rewards_str).expect("Failed to parse GRANT_REWARDS")
+}
+
+pub fn read_approval_required_commands() -> HashSet<String> {
+    let approval_required_commands_str = env::var(APPROVAL_REQUIRED_COMMANDS).expect("APPROVAL_REQUIRED_COMMANDS not set");
+    approval_required_commands_str.split(',')
+        .filter(|s| !s.is_empty())
+        .map(|s| s.to_string())
+        .collect()
+}
+
+pub fn read_unity_request_config() -> UnityRequestConfig {
+    let unity_request_str = env::var(UNITY_REQUEST).expect("UNITY_REQUEST not set");
+    serde_json::from_str(&unity_request_str).expect("Failed to parse UNITY_REQUEST")
+}
+
+pub fn read_unity_environments() -> BTreeMap<String, UnityEnvironmentConfig> {
+    let unity_environments_str = env::var(UNITY_ENVIRONMENTS).expect("UNITY_ENVIRONMENTS not set");
+    serde_json::from_str(&unity_environments_str).expect("Failed to parse UNITY_ENVIRONMENTS")
+}
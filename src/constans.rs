@@ -1,14 +1,39 @@
-pub const SQLITE_DATABASE_PATH: &str = "SQLITE_DATABASE_PATH";
+pub const DATABASE_URL: &str = "DATABASE_URL";
 pub const DISCORD_TOKEN: &str = "DISCORD_TOKEN";
 pub const OWNERS: &str = "OWNERS";
 pub const GIFT_CODE_CHANNEL: &str = "GIFT_CODE_CHANNEL";
 pub const GIFT_CODE_TEST_CHANNEL: &str = "GIFT_CODE_TEST_CHANNEL";
-pub const UNITY_KEY_ID: &str = "UNITY_KEY_ID";
-pub const UNITY_SECRET_KEY: &str = "UNITY_SECRET_KEY";
-pub const UNITY_PROJECT_ID: &str = "UNITY_PROJECT_ID";
-pub const UNITY_ENVIRONMENT_ID: &str = "UNITY_ENVIRONMENT_ID";
+pub const UNITY_ENVIRONMENTS: &str = "UNITY_ENVIRONMENTS";
+pub const PRODUCTION_ENVIRONMENT: &str = "production";
 pub const UNITY_SAVE_DATA_KEY: &str = "UNITY_SAVE_DATA_KEY";
+pub const UNITY_API_BASE_URL: &str = "UNITY_API_BASE_URL";
+pub const DEFAULT_UNITY_API_BASE_URL: &str = "https://services.api.unity.com";
 pub const SUBSCRIPTION_TYPES: &str = "SUBSCRIPTION_TYPES";
 pub const BOT_USER_ID: &str = "BOT_USER_ID";
+pub const RECONCILE_INTERVAL_MINUTES: &str = "RECONCILE_INTERVAL_MINUTES";
+pub const SAVE_DATA_SCHEMA_PATH: &str = "SAVE_DATA_SCHEMA_PATH";
+pub const PLAYER_SUMMARY: &str = "PLAYER_SUMMARY";
+pub const GRANT_REWARDS: &str = "GRANT_REWARDS";
+pub const UNITY_REQUEST: &str = "UNITY_REQUEST";
+pub const APPROVAL_REQUIRED_COMMANDS: &str = "APPROVAL_REQUIRED_COMMANDS";
+pub const APPROVAL_EXPIRY_MINUTES: &str = "APPROVAL_EXPIRY_MINUTES";
+pub const APPROVE_BUTTON_PREFIX: &str = "approval_approve_";
+pub const REJECT_BUTTON_PREFIX: &str = "approval_reject_";
 pub const INTERACTION_LISTENER_RETRY_DELAY: u64 = 60;
+pub const SAVE_HISTORY_LIMIT: u32 = 10;
+pub const GRANT_HISTORY_LIMIT: u32 = 10;
+pub const SCHEMA_VIOLATION_LIMIT: usize = 20;
+pub const CONFIRMATION_TIMEOUT: u64 = 60;
+pub const BULK_CONCURRENCY: usize = 5;
+pub const BULK_ROW_LIMIT: usize = 1000;
+pub const BULK_PROGRESS_INTERVAL: u64 = 3;
+pub const PLAYER_ITEMS_PAGE_LIMIT: usize = 50;
+pub const PLAYER_FILES_PAGE_LIMIT: usize = 50;
+pub const PLAYER_FILE_BACKUP_SIZE_LIMIT: u64 = 8 * 1024 * 1024;
+pub const MESSAGE_LENGTH_LIMIT: usize = 2000;
+pub const EMBED_FIELD_LIMIT: usize = 25;
+pub const EMBED_FIELD_VALUE_LIMIT: usize = 1024;
+pub const EMBED_DESCRIPTION_LIMIT: usize = 4096;
+pub const TOKEN_DEFAULT_LIFETIME: i64 = 3600;
+pub const TOKEN_REFRESH_MARGIN: i64 = 300;
 pub const DISCORD_BOT_CONFIG_PATH: &str = "discord_bot_config.json";
\ No newline at end of file
@@ -1,36 +1,110 @@
 use std::env;
-use sqlx::SqlitePool;
+use chrono::{DateTime, Utc};
+use serde_json::Value;
+use sqlx::any::{install_default_drivers, AnyPoolOptions};
+use sqlx::AnyPool;
 use crate::Error;
-use crate::constans::SQLITE_DATABASE_PATH;
+use crate::constans::DATABASE_URL;
+use crate::migrations::{self, Migration, MigrationStatus};
+use crate::models::{ApprovalRequest, ApprovalStatus, GiftCodeReward, GiftCodeRedemption, GrantRecord, SaveDataWrite, SaveSnapshot};
 
+type ApprovalRow = (i64, String, String, i64, i64, String, Option<i64>, String, String, Option<String>);
+type SaveSnapshotRow = (i64, String, String, String, String, i64, String);
+type GrantRow = (i64, String, String, String, String, i64, i64, String);
+
+// What differs between the databases `Db` runs on. Queries go through sqlx's Any driver and are shared, so a
+// backend only supplies the SQL that can't be.
+pub trait DbBackend: Send + Sync {
+    // Column definition substituted for `AUTO_ID` in migrations.
+    fn auto_id(&self) -> &'static str;
+    fn migration_sql(&self, migration: &Migration) -> &'static str;
+    // Counts the schema_version tables visible to the connection, without creating one.
+    fn schema_version_table_query(&self) -> &'static str;
+}
+
+pub struct SqliteBackend;
+
+impl DbBackend for SqliteBackend {
+    fn auto_id(&self) -> &'static str {
+        "INTEGER PRIMARY KEY AUTOINCREMENT"
+    }
+
+    fn migration_sql(&self, migration: &Migration) -> &'static str {
+        migration.sql
+    }
+
+    fn schema_version_table_query(&self) -> &'static str {
+        "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name = 'schema_version'"
+    }
+}
+
+pub struct PostgresBackend;
+
+impl DbBackend for PostgresBackend {
+    fn auto_id(&self) -> &'static str {
+        "BIGSERIAL PRIMARY KEY"
+    }
+
+    fn migration_sql(&self, migration: &Migration) -> &'static str {
+        migration.postgres_sql.unwrap_or(migration.sql)
+    }
+
+    fn schema_version_table_query(&self) -> &'static str {
+        "SELECT COUNT(*) FROM information_schema.tables WHERE table_schema = current_schema() AND table_name = 'schema_version'"
+    }
+}
+
+pub fn create_db_backend(url: &str) -> Result<Box<dyn DbBackend>, Error> {
+    if url.starts_with("sqlite:") {
+        Ok(Box::new(SqliteBackend))
+    } else if url.starts_with("postgres:") || url.starts_with("postgresql:") {
+        Ok(Box::new(PostgresBackend))
+    } else {
+        Err(format!("Unsupported database URL: {}", url).into())
+    }
+}
+
+// Queries are written once against sqlx's Any driver, so they must stick to SQL both SQLite and Postgres accept
+// and use numbered ($1) placeholders.
 pub struct Db {
-    pool: SqlitePool,
+    pool: AnyPool,
+    backend: Box<dyn DbBackend>,
 }
 
 impl Db {
     pub async fn new() -> Self {
-        let database_path = env::var(SQLITE_DATABASE_PATH).unwrap();
-        let url = format!("sqlite://{}", database_path);
-        Self {
-            pool: SqlitePool::connect(&url).await.unwrap(),
+        let url = env::var(DATABASE_URL).unwrap();
+        Self::connect(&url).await.unwrap()
+    }
+
+    pub async fn connect(url: &str) -> Result<Self, Error> {
+        install_default_drivers();
+        let backend = create_db_backend(url)?;
+
+        let mut options = AnyPoolOptions::new();
+        if url.contains(":memory:") {
+            // Every connection to an in-memory database gets its own empty database, so keep a single one alive.
+            options = options.max_connections(1).idle_timeout(None).max_lifetime(None);
         }
+        let pool = options.connect(url).await?;
+        Ok(Self { pool, backend })
     }
 
-    pub async fn create_tables_if_needed(&self) -> Result<(), Error> {
-        sqlx::query(
-            "CREATE TABLE IF NOT EXISTS user_gift_codes (
-                user_id INTEGER NOT NULL,
-                gift_code_key TEXT NOT NULL,
-                PRIMARY KEY(user_id, gift_code_key)
-            )"
-        ).execute(&self.pool).await?;
-    
-        Ok(())
+    pub fn backend(&self) -> &dyn DbBackend {
+        self.backend.as_ref()
     }
-    
+
+    pub async fn migrate(&self, dry_run: bool) -> Result<Vec<&'static Migration>, Error> {
+        migrations::apply(&self.pool, self.backend(), dry_run).await
+    }
+
+    pub async fn migration_status(&self) -> Result<Vec<MigrationStatus>, Error> {
+        migrations::status(&self.pool, self.backend()).await
+    }
+
     pub async fn is_user_redeemed_gift_code_in_db(&self, gift_code_key: &String, user_id: u64) -> Result<bool, Error> {
         let row: (i64,) = sqlx::query_as(
-            "SELECT COUNT(*) FROM user_gift_codes WHERE user_id = ? AND gift_code_key = ?"
+            "SELECT COUNT(*) FROM user_gift_codes WHERE user_id = $1 AND gift_code_key = $2"
         )
         .bind(user_id as i64)
         .bind(gift_code_key)
@@ -39,15 +113,300 @@ impl Db {
         Ok(row.0 > 0)
     }
     
-    pub async fn redeem_gift_code_in_db(&self, gift_code_key: &String, user_id: u64) -> Result<(), Error> {
+    pub async fn redeem_gift_code_in_db(&self, redemption: &GiftCodeRedemption) -> Result<(), Error> {
         sqlx::query(
-            "INSERT INTO user_gift_codes (user_id, gift_code_key) VALUES (?, ?)"
+            "INSERT INTO user_gift_codes (user_id, gift_code_key, username, guild_id, channel_id, remaining_amount, redeemed_at) VALUES ($1, $2, $3, $4, $5, $6, $7)"
         )
-        .bind(user_id.to_string())
-        .bind(gift_code_key)
+        .bind(redemption.user_id as i64)
+        .bind(&redemption.gift_code_key)
+        .bind(&redemption.username)
+        .bind(redemption.guild_id.map(|id| id as i64))
+        .bind(redemption.channel_id as i64)
+        .bind(redemption.remaining_amount as i64)
+        .bind(&redemption.redeemed_at)
         .execute(&self.pool).await?;
     
         Ok(())
     }
-}
 
+    // A revocation that keeps the unit out of the pool is counted on the gift code, so reconciliation still
+    // expects the lower Cloud Save amount.
+    pub async fn revoke_gift_code_redemption_in_db(&self, gift_code_key: &String, user_id: u64, return_to_pool: bool) -> Result<bool, Error> {
+        let mut tx = self.pool.begin().await?;
+        let result = sqlx::query(
+            "DELETE FROM user_gift_codes WHERE user_id = $1 AND gift_code_key = $2"
+        )
+        .bind(user_id as i64)
+        .bind(gift_code_key)
+        .execute(&mut *tx).await?;
+
+        if result.rows_affected() == 0 {
+            return Ok(false);
+        }
+
+        if !return_to_pool {
+            sqlx::query(
+                "UPDATE gift_codes SET revoked_without_return = revoked_without_return + 1 WHERE gift_code_key = $1"
+            )
+            .bind(gift_code_key)
+            .execute(&mut *tx).await?;
+        }
+
+        tx.commit().await?;
+        Ok(true)
+    }
+
+    pub async fn block_user_in_db(&self, user_id: u64, reason: Option<&str>, expires_at: Option<&str>, blocked_by: u64) -> Result<(), Error> {
+        sqlx::query(
+            "INSERT INTO blocked_users (user_id, reason, expires_at, blocked_by, blocked_at) VALUES ($1, $2, $3, $4, $5)
+            ON CONFLICT (user_id) DO UPDATE SET reason = excluded.reason, expires_at = excluded.expires_at, blocked_by = excluded.blocked_by, blocked_at = excluded.blocked_at"
+        )
+        .bind(user_id as i64)
+        .bind(reason)
+        .bind(expires_at)
+        .bind(blocked_by as i64)
+        .bind(Utc::now().to_rfc3339())
+        .execute(&self.pool).await?;
+
+        Ok(())
+    }
+
+    pub async fn unblock_user_in_db(&self, user_id: u64) -> Result<bool, Error> {
+        let result = sqlx::query(
+            "DELETE FROM blocked_users WHERE user_id = $1"
+        )
+        .bind(user_id as i64)
+        .execute(&self.pool).await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn is_user_blocked_in_db(&self, user_id: u64) -> Result<bool, Error> {
+        let row: Option<(Option<String>,)> = sqlx::query_as(
+            "SELECT expires_at FROM blocked_users WHERE user_id = $1"
+        )
+        .bind(user_id as i64)
+        .fetch_optional(&self.pool).await?;
+
+        match row {
+            None => Ok(false),
+            Some((None,)) => Ok(true),
+            Some((Some(expires_at),)) => {
+                let expires_at = DateTime::parse_from_rfc3339(&expires_at)?.with_timezone(&Utc);
+                Ok(expires_at > Utc::now())
+            }
+        }
+    }
+
+    pub async fn record_gift_code_in_db(&self, gift_code_key: &String, initial_amount: u32) -> Result<(), Error> {
+        sqlx::query(
+            "INSERT INTO gift_codes (gift_code_key, initial_amount, created_at) VALUES ($1, $2, $3)
+            ON CONFLICT (gift_code_key) DO UPDATE SET initial_amount = excluded.initial_amount"
+        )
+        .bind(gift_code_key)
+        .bind(initial_amount as i64)
+        .bind(Utc::now().to_rfc3339())
+        .execute(&self.pool).await?;
+
+        Ok(())
+    }
+
+    pub async fn get_gift_code_initial_amount_in_db(&self, gift_code_key: &String) -> Result<Option<u32>, Error> {
+        let row: Option<(i64,)> = sqlx::query_as(
+            "SELECT initial_amount FROM gift_codes WHERE gift_code_key = $1"
+        )
+        .bind(gift_code_key)
+        .fetch_optional(&self.pool).await?;
+
+        Ok(row.map(|(initial_amount,)| initial_amount as u32))
+    }
+
+    pub async fn count_gift_code_revocations_in_db(&self, gift_code_key: &String) -> Result<u32, Error> {
+        let row: Option<(i64,)> = sqlx::query_as(
+            "SELECT revoked_without_return FROM gift_codes WHERE gift_code_key = $1"
+        )
+        .bind(gift_code_key)
+        .fetch_optional(&self.pool).await?;
+
+        Ok(row.map_or(0, |(revoked,)| revoked as u32))
+    }
+
+    pub async fn count_gift_code_redemptions_in_db(&self, gift_code_key: &String) -> Result<u32, Error> {
+        let row: (i64,) = sqlx::query_as(
+            "SELECT COUNT(*) FROM user_gift_codes WHERE gift_code_key = $1"
+        )
+        .bind(gift_code_key)
+        .fetch_one(&self.pool).await?;
+
+        Ok(row.0 as u32)
+    }
+
+    pub async fn insert_save_snapshot_in_db(&self, player_id: &str, environment: &str, save_data: &Value, write: &SaveDataWrite) -> Result<i64, Error> {
+        let row: (i64,) = sqlx::query_as(
+            "INSERT INTO save_snapshots (player_id, environment, save_data, command, actor_id, created_at) VALUES ($1, $2, $3, $4, $5, $6) RETURNING id"
+        )
+        .bind(player_id)
+        .bind(environment)
+        .bind(serde_json::to_string(save_data)?)
+        .bind(&write.command)
+        .bind(write.actor_id as i64)
+        .bind(Utc::now().to_rfc3339())
+        .fetch_one(&self.pool).await?;
+
+        Ok(row.0)
+    }
+
+    pub async fn get_save_snapshots_in_db(&self, player_id: &str, environment: &str, limit: u32) -> Result<Vec<SaveSnapshot>, Error> {
+        let rows: Vec<SaveSnapshotRow> = sqlx::query_as(
+            "SELECT id, player_id, environment, save_data, command, actor_id, created_at FROM save_snapshots WHERE player_id = $1 AND environment = $2 ORDER BY id DESC LIMIT $3"
+        )
+        .bind(player_id)
+        .bind(environment)
+        .bind(limit as i64)
+        .fetch_all(&self.pool).await?;
+
+        Ok(rows.into_iter().map(Self::to_save_snapshot).collect())
+    }
+
+    // Scoped to the environment too, so a staging snapshot can't be restored over a production save.
+    pub async fn get_save_snapshot_in_db(&self, player_id: &str, environment: &str, snapshot_id: i64) -> Result<Option<SaveSnapshot>, Error> {
+        let row: Option<SaveSnapshotRow> = sqlx::query_as(
+            "SELECT id, player_id, environment, save_data, command, actor_id, created_at FROM save_snapshots WHERE player_id = $1 AND environment = $2 AND id = $3"
+        )
+        .bind(player_id)
+        .bind(environment)
+        .bind(snapshot_id)
+        .fetch_optional(&self.pool).await?;
+
+        Ok(row.map(Self::to_save_snapshot))
+    }
+
+    fn to_save_snapshot((id, player_id, environment, save_data, command, actor_id, created_at): SaveSnapshotRow) -> SaveSnapshot {
+        SaveSnapshot {
+            id,
+            player_id,
+            environment,
+            save_data,
+            command,
+            actor_id: actor_id as u64,
+            created_at,
+        }
+    }
+
+    pub async fn insert_grant_in_db(&self, player_id: &str, environment: &str, rewards: &GiftCodeReward, reason: &str, snapshot_id: i64, actor_id: u64) -> Result<i64, Error> {
+        let row: (i64,) = sqlx::query_as(
+            "INSERT INTO grants (player_id, environment, rewards, reason, snapshot_id, actor_id, created_at) VALUES ($1, $2, $3, $4, $5, $6, $7) RETURNING id"
+        )
+        .bind(player_id)
+        .bind(environment)
+        .bind(serde_json::to_string(rewards)?)
+        .bind(reason)
+        .bind(snapshot_id)
+        .bind(actor_id as i64)
+        .bind(Utc::now().to_rfc3339())
+        .fetch_one(&self.pool).await?;
+
+        Ok(row.0)
+    }
+
+    pub async fn delete_grant_in_db(&self, grant_id: i64) -> Result<(), Error> {
+        sqlx::query("DELETE FROM grants WHERE id = $1")
+            .bind(grant_id)
+            .execute(&self.pool).await?;
+
+        Ok(())
+    }
+
+    // Scoped to the environment like snapshots, so the snapshot ids listed belong to the same environment.
+    pub async fn get_grants_in_db(&self, player_id: &str, environment: &str, limit: u32) -> Result<Vec<GrantRecord>, Error> {
+        let rows: Vec<GrantRow> = sqlx::query_as(
+            "SELECT id, player_id, environment, rewards, reason, snapshot_id, actor_id, created_at FROM grants WHERE player_id = $1 AND environment = $2 ORDER BY id DESC LIMIT $3"
+        )
+        .bind(player_id)
+        .bind(environment)
+        .bind(limit as i64)
+        .fetch_all(&self.pool).await?;
+
+        Ok(rows.into_iter().map(|(id, player_id, environment, rewards, reason, snapshot_id, actor_id, created_at)| GrantRecord {
+            id,
+            player_id,
+            environment,
+            rewards,
+            reason,
+            snapshot_id,
+            actor_id: actor_id as u64,
+            created_at,
+        }).collect())
+    }
+
+    pub async fn insert_approval_in_db(&self, command: &str, params: &Value, requested_by: u64, channel_id: u64, expires_at: &str) -> Result<i64, Error> {
+        let row: (i64,) = sqlx::query_as(
+            "INSERT INTO pending_approvals (command, params, requested_by, channel_id, status, created_at, expires_at) VALUES ($1, $2, $3, $4, $5, $6, $7) RETURNING id"
+        )
+        .bind(command)
+        .bind(serde_json::to_string(params)?)
+        .bind(requested_by as i64)
+        .bind(channel_id as i64)
+        .bind(ApprovalStatus::Pending.to_string())
+        .bind(Utc::now().to_rfc3339())
+        .bind(expires_at)
+        .fetch_one(&self.pool).await?;
+
+        Ok(row.0)
+    }
+
+    pub async fn get_approval_in_db(&self, approval_id: i64) -> Result<Option<ApprovalRequest>, Error> {
+        let row: Option<ApprovalRow> = sqlx::query_as(
+            "SELECT id, command, params, requested_by, channel_id, status, decided_by, created_at, expires_at, outcome FROM pending_approvals WHERE id = $1"
+        )
+        .bind(approval_id)
+        .fetch_optional(&self.pool).await?;
+
+        let Some((id, command, params, requested_by, channel_id, status, decided_by, created_at, expires_at, outcome)) = row else {
+            return Ok(None);
+        };
+        Ok(Some(ApprovalRequest {
+            id,
+            command,
+            params,
+            requested_by: requested_by as u64,
+            channel_id: channel_id as u64,
+            status: status.parse()?,
+            decided_by: decided_by.map(|decided_by| decided_by as u64),
+            created_at,
+            expires_at,
+            outcome,
+        }))
+    }
+
+    // Only moves a pending request, so two owners clicking at once can't both decide it. Returns false
+    // if the request was already decided.
+    pub async fn decide_approval_in_db(&self, approval_id: i64, status: ApprovalStatus, decided_by: u64) -> Result<bool, Error> {
+        let result = sqlx::query(
+            "UPDATE pending_approvals SET status = $1, decided_by = $2, decided_at = $3 WHERE id = $4 AND status = $5"
+        )
+        .bind(status.to_string())
+        .bind(decided_by as i64)
+        .bind(Utc::now().to_rfc3339())
+        .bind(approval_id)
+        .bind(ApprovalStatus::Pending.to_string())
+        .execute(&self.pool).await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    // Records how an approved command went. A request left as approved was claimed but the bot stopped
+    // before the command finished, so the save has to be checked by hand.
+    pub async fn finish_approval_in_db(&self, approval_id: i64, status: ApprovalStatus, outcome: &str) -> Result<bool, Error> {
+        let result = sqlx::query(
+            "UPDATE pending_approvals SET status = $1, outcome = $2 WHERE id = $3 AND status = $4"
+        )
+        .bind(status.to_string())
+        .bind(outcome)
+        .bind(approval_id)
+        .bind(ApprovalStatus::Approved.to_string())
+        .execute(&self.pool).await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+}
@@ -0,0 +1,167 @@
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use anyhow::anyhow;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde_json::Value;
+use crate::cloud_save::{get_local_page, validate_key, CloudSave};
+use crate::models::{AccessClass, ItemTimestamp, PageLinks, PlayerFile, PlayerFilesPage, PlayerItem, PlayerItemsPage};
+use crate::unity_error::UnityError;
+use crate::Error;
+
+// Reads and writes a directory laid out like the Cloud Save URLs, so saves can be dropped in and edited by
+// hand for offline development:
+//   custom/{customId}/{key}.json
+//   players/{playerId}/items/{key}.json (or public/items, protected/items, private/items)
+//   players/{playerId}/files/{key}
+pub struct DirectoryCloudSave {
+    root: PathBuf,
+}
+
+impl DirectoryCloudSave {
+    pub fn new(root: &str) -> Result<Self, Error> {
+        fs::create_dir_all(root)?;
+        Ok(Self { root: PathBuf::from(root) })
+    }
+
+    fn custom_dir(&self, custom_id: &str) -> Result<PathBuf, Error> {
+        validate_key(custom_id)?;
+        Ok(self.root.join("custom").join(custom_id))
+    }
+
+    fn player_items_dir(&self, player_id: &str, access_class: AccessClass) -> Result<PathBuf, Error> {
+        validate_key(player_id)?;
+        Ok(self.root.join("players").join(player_id).join(access_class.items_path()))
+    }
+
+    fn player_files_dir(&self, player_id: &str) -> Result<PathBuf, Error> {
+        validate_key(player_id)?;
+        Ok(self.root.join("players").join(player_id).join("files"))
+    }
+}
+
+fn get_modified(path: &Path) -> Result<Option<ItemTimestamp>, Error> {
+    let modified: DateTime<Utc> = fs::metadata(path)?.modified()?.into();
+    Ok(Some(ItemTimestamp { date: modified.to_rfc3339() }))
+}
+
+// A missing directory just means nothing has been stored there yet.
+fn read_dir_entries(dir: &Path) -> Result<Vec<PathBuf>, Error> {
+    match fs::read_dir(dir) {
+        Ok(entries) => Ok(entries
+            .map(|entry| entry.map(|entry| entry.path()))
+            .collect::<Result<Vec<PathBuf>, _>>()?
+            .into_iter()
+            .filter(|path| path.is_file())
+            .collect()),
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(vec![]),
+        Err(e) => Err(e.into()),
+    }
+}
+
+fn read_items(dir: &Path) -> Result<Vec<(String, PlayerItem)>, Error> {
+    let mut items = vec![];
+    for path in read_dir_entries(dir)? {
+        if path.extension().is_none_or(|extension| extension != "json") {
+            continue;
+        }
+        let key = path.file_stem().unwrap_or_default().to_string_lossy().to_string();
+        let value: Value = serde_json::from_str(&fs::read_to_string(&path)?)
+            .map_err(|e| anyhow!("Invalid JSON in {}: {}", path.display(), e))?;
+        items.push((key.clone(), PlayerItem {
+            key,
+            value,
+            write_lock: None,
+            modified: get_modified(&path)?,
+            created: None,
+        }));
+    }
+    Ok(items)
+}
+
+fn write_item(dir: &Path, key: &str, value: &Value) -> Result<(), Error> {
+    validate_key(key)?;
+    fs::create_dir_all(dir)?;
+    fs::write(dir.join(format!("{}.json", key)), serde_json::to_string_pretty(value)?)?;
+    Ok(())
+}
+
+fn remove_file(path: &Path, context: &str) -> Result<(), Error> {
+    match fs::remove_file(path) {
+        Ok(()) => Ok(()),
+        Err(e) if e.kind() == ErrorKind::NotFound => Err(UnityError::not_found(context, &path.display().to_string()).into()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+#[async_trait]
+impl CloudSave for DirectoryCloudSave {
+    async fn get_custom_items(&self, custom_id: &str, keys: Option<&str>) -> Result<Vec<PlayerItem>, Error> {
+        Ok(get_local_page(read_items(&self.custom_dir(custom_id)?)?, keys, None))
+    }
+
+    async fn set_custom_item(&self, custom_id: &str, key: &str, value: Value) -> Result<(), Error> {
+        write_item(&self.custom_dir(custom_id)?, key, &value)
+    }
+
+    async fn delete_custom_item(&self, custom_id: &str, key: &str) -> Result<(), Error> {
+        validate_key(key)?;
+        let path = self.custom_dir(custom_id)?.join(format!("{}.json", key));
+        remove_file(&path, &format!("Failed to delete {} item {}", custom_id, key))
+    }
+
+    async fn get_player_items_page(&self, player_id: &str, keys: Option<&str>, after: Option<&str>, access_class: AccessClass) -> Result<PlayerItemsPage, Error> {
+        let items = read_items(&self.player_items_dir(player_id, access_class)?)?;
+        Ok(PlayerItemsPage {
+            results: get_local_page(items, keys, after),
+            links: PageLinks::default(),
+        })
+    }
+
+    async fn set_player_item(&self, player_id: &str, key: &str, value: Value, access_class: AccessClass) -> Result<(), Error> {
+        write_item(&self.player_items_dir(player_id, access_class)?, key, &value)
+    }
+
+    async fn get_player_files_page(&self, player_id: &str, after: Option<&str>) -> Result<PlayerFilesPage, Error> {
+        let mut files = vec![];
+        for path in read_dir_entries(&self.player_files_dir(player_id)?)? {
+            let key = path.file_name().unwrap_or_default().to_string_lossy().to_string();
+            files.push((key.clone(), PlayerFile {
+                key,
+                size: fs::metadata(&path)?.len(),
+                content_type: None,
+                write_lock: None,
+                modified: get_modified(&path)?,
+            }));
+        }
+        Ok(PlayerFilesPage {
+            results: get_local_page(files, None, after),
+            links: PageLinks::default(),
+        })
+    }
+
+    async fn download_player_file(&self, player_id: &str, key: &str) -> Result<Vec<u8>, Error> {
+        validate_key(key)?;
+        let path = self.player_files_dir(player_id)?.join(key);
+        match fs::read(&path) {
+            Ok(content) => Ok(content),
+            Err(e) if e.kind() == ErrorKind::NotFound => Err(UnityError::not_found(&format!("Failed to get player file {}", key), &path.display().to_string()).into()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn upload_player_file(&self, player_id: &str, key: &str, content: Vec<u8>, _content_type: &str) -> Result<(), Error> {
+        validate_key(key)?;
+        let dir = self.player_files_dir(player_id)?;
+        fs::create_dir_all(&dir)?;
+        fs::write(dir.join(key), content)?;
+        Ok(())
+    }
+
+    async fn delete_player_file(&self, player_id: &str, key: &str) -> Result<(), Error> {
+        validate_key(key)?;
+        let path = self.player_files_dir(player_id)?.join(key);
+        remove_file(&path, &format!("Failed to delete player file {}", key))
+    }
+}
@@ -0,0 +1,64 @@
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use anyhow::anyhow;
+use crate::config::read_unity_environments;
+use crate::constans::PRODUCTION_ENVIRONMENT;
+use crate::db::Db;
+use crate::unity_service::UnityService;
+use crate::Error;
+
+// A `UnityService` per configured environment. Commands pick one with their `environment` option and
+// default to production.
+pub struct UnityEnvironments {
+    services: BTreeMap<String, Arc<UnityService>>,
+}
+
+impl UnityEnvironments {
+    pub fn new(db: Arc<Db>) -> Result<Self, Error> {
+        let mut services = BTreeMap::new();
+        for (name, config) in read_unity_environments() {
+            let unity_service = UnityService::new(&name, &config, db.clone())?;
+            services.insert(name, Arc::new(unity_service));
+        }
+        UnityEnvironments::from_services(services)
+    }
+
+    pub fn from_services(services: BTreeMap<String, Arc<UnityService>>) -> Result<Self, Error> {
+        if !services.contains_key(PRODUCTION_ENVIRONMENT) {
+            return Err(anyhow!("No {} environment configured", PRODUCTION_ENVIRONMENT).into());
+        }
+        Ok(Self { services })
+    }
+
+    pub fn production(&self) -> Arc<UnityService> {
+        self.services[PRODUCTION_ENVIRONMENT].clone()
+    }
+
+    pub fn get(&self, environment: &str) -> Result<Arc<UnityService>, Error> {
+        self.services.get(environment).cloned().ok_or_else(|| {
+            anyhow!("Unknown environment: {}. Configured environments: {}", environment, self.names().join(", ")).into()
+        })
+    }
+
+    pub fn names(&self) -> Vec<String> {
+        self.services.keys().cloned().collect()
+    }
+
+    pub fn all(&self) -> Vec<Arc<UnityService>> {
+        self.services.values().cloned().collect()
+    }
+
+    // Reads default to production. Once other environments are configured, a write without an
+    // environment could be meant for any of them, so production has to be named explicitly.
+    pub fn select(&self, environment: Option<&str>, write: bool) -> Result<Arc<UnityService>, Error> {
+        match environment {
+            Some(environment) => self.get(environment),
+            None if write && self.services.len() > 1 => Err(anyhow!(
+                "This command writes to Cloud Save. Pass environment: {} to write to production, or one of: {}",
+                PRODUCTION_ENVIRONMENT,
+                self.names().join(", ")
+            ).into()),
+            None => Ok(self.production()),
+        }
+    }
+}
@@ -11,7 +11,7 @@ pub fn is_valid_gift_code_reward(value: &JsonValue) -> bool {
 }
 
 pub fn is_valid_gift_code(code: &str) -> bool {
-    code.len() == 16 && code.chars().all(|c| c.is_ascii_uppercase() || c.is_digit(10))
+    code.len() == 16 && code.chars().all(|c| c.is_ascii_uppercase() || c.is_ascii_digit())
 }
 
 pub fn generate_gift_code() -> String {
@@ -40,13 +40,13 @@ pub fn validate_gift_code(
         Err(anyhow!("Title cannot be empty").into())
     } else if subtitle.is_empty() {
         Err(anyhow!("Subtitle cannot be empty").into())
-    } else if amount <= 0 {
+    } else if amount == 0 {
         Err(anyhow!("Amount cannot be 0").into())
-    } else if duration <= 0 {
+    } else if duration == 0 {
         Err(anyhow!("Duration cannot be 0").into())
-    } else if rewards.is_null() || rewards.as_object().map_or(true, |o| o.is_empty()) {
+    } else if rewards.is_null() || rewards.as_object().is_none_or(|o| o.is_empty()) {
         Err(anyhow!("Rewards cannot be empty").into())
-    } else if is_valid_gift_code_reward(rewards) == false {
+    } else if !is_valid_gift_code_reward(rewards) {
         Err(anyhow!("Invalid gift code reward").into())
     } else {
         Ok(())
@@ -98,7 +98,7 @@ pub fn get_gift_code_message(gift_code: &GiftCode) -> String {
     let mut message = format!("**{}**\n{}\n", gift_code.title, gift_code.subtitle);
 
 
-    message.push_str("\n");
+    message.push('\n');
 
     let parsed_date = match DateTime::parse_from_rfc3339(&gift_code.expired_at) {
         Ok(parsed_date) => {
@@ -116,20 +116,20 @@ pub fn get_gift_code_message(gift_code: &GiftCode) -> String {
     }
 
     if gift_code.rewards.xp_reward > 0 {
-        if currency_rewards.len() > 0 {
+        if !currency_rewards.is_empty() {
             message.push_str("     ");
         }
         let formatted_xp = format!("{:.3}", gift_code.rewards.xp_reward as f32 / 1000.0);
         message.push_str(&format!("**<:xp:1250546574518521916> x {}**", formatted_xp));
     }
 
-    message.push_str("\n");
+    message.push('\n');
 
     for item in &gift_code.rewards.item_rewards {
         message.push_str(&format!("{}\n", item.name));
     }
 
-    message.push_str("\n");
+    message.push('\n');
 
     message.push_str(&format!("Remaining Gift Codes: {}               ", gift_code.amount));
 
@@ -139,6 +139,6 @@ pub fn get_gift_code_message(gift_code: &GiftCode) -> String {
         message.push_str(&format!("Expiration: {}\n", friendly_date));
     }
 
-    message.push_str("\n");
+    message.push('\n');
     message
 }
\ No newline at end of file
@@ -0,0 +1,229 @@
+use std::env;
+use anyhow::anyhow;
+use async_trait::async_trait;
+use base64::encode;
+use md5::{Digest, Md5};
+use serde_json::Value;
+use crate::cloud_save::CloudSave;
+use crate::config::{read_unity_request_config, UnityEnvironmentConfig};
+use crate::constans::UNITY_API_BASE_URL;
+use crate::models::{AccessClass, FileUploadRequest, PlayerFilesPage, PlayerItem, PlayerItemsPage, SaveValueRequest, SignedUrlResponse};
+use crate::unity_auth::UnityTokenProvider;
+use crate::unity_error::UnityError;
+use crate::unity_request::UnityRequestExecutor;
+use crate::Error;
+
+// Cloud Save through Unity's REST API.
+pub struct HttpCloudSave {
+    client: reqwest::Client,
+    custom_url: String,
+    players_url: String,
+    files_url: String,
+    executor: UnityRequestExecutor,
+}
+
+impl HttpCloudSave {
+    pub fn new(environment: &UnityEnvironmentConfig) -> Result<Self, Error> {
+        let request_config = read_unity_request_config();
+        let client = reqwest::Client::builder()
+            .timeout(std::time::Duration::from_secs(request_config.timeout_seconds))
+            .build()?;
+
+        let base_url = env::var(UNITY_API_BASE_URL)?;
+        let project_id = HttpCloudSave::get_credential(&environment.unity_project_id, "unity_project_id")?;
+        let environment_id = HttpCloudSave::get_credential(&environment.unity_environment_id, "unity_environment_id")?;
+        let auth = UnityTokenProvider::new(
+            client.clone(),
+            &base_url,
+            project_id,
+            environment_id,
+            HttpCloudSave::get_credential(&environment.unity_key_id, "unity_key_id")?,
+            HttpCloudSave::get_credential(&environment.unity_secret_key, "unity_secret_key")?,
+        );
+
+        let data_url = format!("{}/cloud-save/v1/data/projects/{}/environments/{}", base_url, project_id, environment_id);
+        let files_url = format!("{}/cloud-save/v1/files/projects/{}/environments/{}", base_url, project_id, environment_id);
+        Ok(Self {
+            custom_url: format!("{}/custom", data_url),
+            players_url: format!("{}/players", data_url),
+            files_url: format!("{}/players", files_url),
+            executor: UnityRequestExecutor::new(client.clone(), auth, request_config),
+            client,
+        })
+    }
+
+    fn get_credential<'a>(value: &'a Option<String>, name: &str) -> Result<&'a str, Error> {
+        value.as_deref().ok_or_else(|| anyhow!("{} is required for the http cloud save backend", name).into())
+    }
+}
+
+#[async_trait]
+impl CloudSave for HttpCloudSave {
+    async fn get_custom_items(&self, custom_id: &str, keys: Option<&str>) -> Result<Vec<PlayerItem>, Error> {
+        let get_url = format!("{}/{}/items", self.custom_url, custom_id);
+
+        let mut request = self.client.get(&get_url);
+        if let Some(keys) = keys {
+            request = request.query(&[("keys", keys)]);
+        }
+
+        let response = self.executor.send(request).await?;
+        if response.status().is_success() {
+            let page: PlayerItemsPage = response.json().await?;
+            Ok(page.results)
+        } else {
+            Err(UnityError::from_response(&format!("Failed to get {} items", custom_id), response).await.into())
+        }
+    }
+
+    async fn set_custom_item(&self, custom_id: &str, key: &str, value: Value) -> Result<(), Error> {
+        let save_url = format!("{}/{}/items", self.custom_url, custom_id);
+
+        let request_body = SaveValueRequest {
+            key: key.to_string(),
+            value,
+        };
+
+        let response = self.executor.send(self.client.post(&save_url)
+            .json(&request_body)).await?;
+
+        if response.status().is_success() {
+            Ok(())
+        } else {
+            Err(UnityError::from_response(&format!("Failed to save {} item {}", custom_id, key), response).await.into())
+        }
+    }
+
+    async fn delete_custom_item(&self, custom_id: &str, key: &str) -> Result<(), Error> {
+        let delete_url = format!("{}/{}/items/{}", self.custom_url, custom_id, key);
+
+        let response = self.executor.send(self.client.delete(&delete_url)).await?;
+
+        if response.status().is_success() {
+            Ok(())
+        } else {
+            Err(UnityError::from_response(&format!("Failed to delete {} item {}", custom_id, key), response).await.into())
+        }
+    }
+
+    async fn get_player_items_page(&self, player_id: &str, keys: Option<&str>, after: Option<&str>, access_class: AccessClass) -> Result<PlayerItemsPage, Error> {
+        let get_url = format!("{}/{}/{}", self.players_url, player_id, access_class.items_path());
+
+        let mut request = self.client.get(&get_url);
+        if let Some(keys) = keys {
+            request = request.query(&[("keys", keys)]);
+        }
+        if let Some(after) = after {
+            request = request.query(&[("after", after)]);
+        }
+
+        let response = self.executor.send(request).await?;
+        if response.status().is_success() {
+            let page: PlayerItemsPage = response.json().await?;
+            Ok(page)
+        } else {
+            Err(UnityError::from_response("Failed to get player items", response).await.into())
+        }
+    }
+
+    async fn set_player_item(&self, player_id: &str, key: &str, value: Value, access_class: AccessClass) -> Result<(), Error> {
+        let save_url = format!("{}/{}/{}", self.players_url, player_id, access_class.items_path());
+
+        let request_body = SaveValueRequest {
+            key: key.to_string(),
+            value,
+        };
+
+        let response = self.executor.send(self.client.post(&save_url)
+            .json(&request_body)).await?;
+
+        if response.status().is_success() {
+            Ok(())
+        } else {
+            Err(UnityError::from_response("Failed to save player item", response).await.into())
+        }
+    }
+
+    async fn get_player_files_page(&self, player_id: &str, after: Option<&str>) -> Result<PlayerFilesPage, Error> {
+        let get_url = format!("{}/{}/files", self.files_url, player_id);
+
+        let mut request = self.client.get(&get_url);
+        if let Some(after) = after {
+            request = request.query(&[("after", after)]);
+        }
+
+        let response = self.executor.send(request).await?;
+        if response.status().is_success() {
+            let page: PlayerFilesPage = response.json().await?;
+            Ok(page)
+        } else {
+            Err(UnityError::from_response("Failed to get player files", response).await.into())
+        }
+    }
+
+    // Cloud Save hands out a signed URL for the file, the content itself is fetched from there without
+    // our credentials.
+    async fn download_player_file(&self, player_id: &str, key: &str) -> Result<Vec<u8>, Error> {
+        let get_url = format!("{}/{}/files/{}", self.files_url, player_id, key);
+
+        let response = self.executor.send(self.client.get(&get_url)).await?;
+
+        if !response.status().is_success() {
+            return Err(UnityError::from_response(&format!("Failed to get player file {}", key), response).await.into());
+        }
+
+        let signed_url: SignedUrlResponse = response.json().await?;
+        let response = self.client.get(&signed_url.signed_url).send().await?;
+        if response.status().is_success() {
+            Ok(response.bytes().await?.to_vec())
+        } else {
+            Err(UnityError::from_response(&format!("Failed to download player file {}", key), response).await.into())
+        }
+    }
+
+    // Uploads go through a signed URL too. The URL is bound to the declared type, length and MD5, so the
+    // PUT has to repeat them.
+    async fn upload_player_file(&self, player_id: &str, key: &str, content: Vec<u8>, content_type: &str) -> Result<(), Error> {
+        let upload_url = format!("{}/{}/files/{}", self.files_url, player_id, key);
+        let content_md5 = encode(Md5::digest(&content));
+
+        let request_body = FileUploadRequest {
+            content_type: content_type.to_string(),
+            content_length: content.len(),
+            content_md5: content_md5.clone(),
+        };
+
+        let response = self.executor.send(self.client.post(&upload_url)
+            .json(&request_body)).await?;
+
+        if !response.status().is_success() {
+            return Err(UnityError::from_response(&format!("Failed to get upload URL for player file {}", key), response).await.into());
+        }
+
+        let signed_url: SignedUrlResponse = response.json().await?;
+        let response = self.client.put(&signed_url.signed_url)
+            .header("Content-Type", content_type)
+            .header("Content-MD5", content_md5)
+            .body(content)
+            .send()
+            .await?;
+
+        if response.status().is_success() {
+            Ok(())
+        } else {
+            Err(UnityError::from_response(&format!("Failed to upload player file {}", key), response).await.into())
+        }
+    }
+
+    async fn delete_player_file(&self, player_id: &str, key: &str) -> Result<(), Error> {
+        let delete_url = format!("{}/{}/files/{}", self.files_url, player_id, key);
+
+        let response = self.executor.send(self.client.delete(&delete_url)).await?;
+
+        if response.status().is_success() {
+            Ok(())
+        } else {
+            Err(UnityError::from_response(&format!("Failed to delete player file {}", key), response).await.into())
+        }
+    }
+}
@@ -0,0 +1,155 @@
+use std::fmt;
+use poise::serenity_prelude::{CreateEmbed, CreateEmbedFooter};
+use serde::Serialize;
+use serde_json::Value;
+
+const EMBED_DIFF_ENTRY_LIMIT: usize = 15;
+const EMBED_VALUE_LENGTH_LIMIT: usize = 100;
+// Discord rejects field names over 256 characters, leave room for the kind and the backticks.
+const EMBED_PATH_LENGTH_LIMIT: usize = 200;
+// Discord also caps the text of a whole embed at 6000 characters. The footer isn't known up front, so room is kept for it.
+const EMBED_TOTAL_LENGTH_LIMIT: usize = 6000;
+const EMBED_FOOTER_LENGTH_RESERVE: usize = 100;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum DiffKind {
+    Added,
+    Removed,
+    Changed,
+}
+
+impl fmt::Display for DiffKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DiffKind::Added => write!(f, "Added"),
+            DiffKind::Removed => write!(f, "Removed"),
+            DiffKind::Changed => write!(f, "Changed"),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiffEntry {
+    pub kind: DiffKind,
+    pub path: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub old_value: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub new_value: Option<Value>,
+}
+
+// Paths are JSON pointers (RFC 6901), so they can be fed straight back into the save editing commands.
+pub fn diff_json(old: &Value, new: &Value) -> Vec<DiffEntry> {
+    let mut entries = vec![];
+    diff_values("", old, new, &mut entries);
+    entries
+}
+
+fn diff_values(path: &str, old: &Value, new: &Value, entries: &mut Vec<DiffEntry>) {
+    match (old, new) {
+        (Value::Object(old_map), Value::Object(new_map)) => {
+            for (key, old_value) in old_map {
+                let child_path = format!("{}/{}", path, escape_pointer_token(key));
+                match new_map.get(key) {
+                    Some(new_value) => diff_values(&child_path, old_value, new_value, entries),
+                    None => entries.push(removed(child_path, old_value)),
+                }
+            }
+            for (key, new_value) in new_map {
+                if !old_map.contains_key(key) {
+                    entries.push(added(format!("{}/{}", path, escape_pointer_token(key)), new_value));
+                }
+            }
+        },
+        (Value::Array(old_array), Value::Array(new_array)) => {
+            for (index, old_value) in old_array.iter().enumerate() {
+                let child_path = format!("{}/{}", path, index);
+                match new_array.get(index) {
+                    Some(new_value) => diff_values(&child_path, old_value, new_value, entries),
+                    None => entries.push(removed(child_path, old_value)),
+                }
+            }
+            for (index, new_value) in new_array.iter().enumerate().skip(old_array.len()) {
+                entries.push(added(format!("{}/{}", path, index), new_value));
+            }
+        },
+        _ if old != new => entries.push(DiffEntry {
+            kind: DiffKind::Changed,
+            path: path.to_string(),
+            old_value: Some(old.clone()),
+            new_value: Some(new.clone()),
+        }),
+        _ => {},
+    }
+}
+
+fn added(path: String, value: &Value) -> DiffEntry {
+    DiffEntry { kind: DiffKind::Added, path, old_value: None, new_value: Some(value.clone()) }
+}
+
+fn removed(path: String, value: &Value) -> DiffEntry {
+    DiffEntry { kind: DiffKind::Removed, path, old_value: Some(value.clone()), new_value: None }
+}
+
+fn escape_pointer_token(token: &str) -> String {
+    token.replace('~', "~0").replace('/', "~1")
+}
+
+fn format_diff_value(value: &Option<Value>) -> String {
+    let formatted = match value {
+        Some(value) => value.to_string(),
+        None => "-".to_string(),
+    };
+    truncate(formatted, EMBED_VALUE_LENGTH_LIMIT)
+}
+
+// Keeps the first `limit` characters and marks the cut with an ellipsis.
+pub(crate) fn truncate(text: String, limit: usize) -> String {
+    if text.chars().count() > limit {
+        format!("{}…", text.chars().take(limit).collect::<String>())
+    } else {
+        text
+    }
+}
+
+pub fn get_diff_embed(title: &str, entries: &[DiffEntry]) -> CreateEmbed {
+    let count = |kind: DiffKind| entries.iter().filter(|entry| entry.kind == kind).count();
+    let counts = [
+        ("Added", count(DiffKind::Added).to_string()),
+        ("Removed", count(DiffKind::Removed).to_string()),
+        ("Changed", count(DiffKind::Changed).to_string()),
+    ];
+
+    let mut length = title.chars().count() + EMBED_FOOTER_LENGTH_RESERVE;
+    let mut embed = CreateEmbed::default().title(title);
+    for (name, value) in counts {
+        length += name.len() + value.len();
+        embed = embed.field(name, value, true);
+    }
+
+    if entries.is_empty() {
+        return embed.description("No differences found.");
+    }
+
+    let mut shown = 0;
+    for entry in entries.iter().take(EMBED_DIFF_ENTRY_LIMIT) {
+        let name = format!("{} `{}`", entry.kind, truncate(entry.path.clone(), EMBED_PATH_LENGTH_LIMIT));
+        let value = format!("`{}` → `{}`", format_diff_value(&entry.old_value), format_diff_value(&entry.new_value));
+        length += name.chars().count() + value.chars().count();
+        if length > EMBED_TOTAL_LENGTH_LIMIT {
+            break;
+        }
+        embed = embed.field(name, value, false);
+        shown += 1;
+    }
+
+    if entries.len() > shown {
+        embed = embed.footer(CreateEmbedFooter::new(
+            format!("…and {} more differences in the attached file", entries.len() - shown)
+        ));
+    }
+
+    embed
+}
@@ -1,9 +1,9 @@
 use std::sync::Arc;
-use unity_service::UnityService;
+use environments::UnityEnvironments;
 
 pub struct ContextData {
     pub bot: Arc<bot::Bot>,
-    pub unity_service: Arc<UnityService>
+    pub unity_environments: Arc<UnityEnvironments>
 }
 
 pub type Error = Box<dyn std::error::Error + Send + Sync>;
@@ -14,6 +14,21 @@ pub mod gift_code;
 pub mod commands;
 pub mod bot;
 pub mod db;
+pub mod migrations;
 pub mod config;
 pub mod constans;
-pub mod models;
\ No newline at end of file
+pub mod models;
+pub mod player_summary;
+pub mod json_diff;
+pub mod reconcile;
+pub mod save_data;
+pub mod bulk;
+pub mod approvals;
+pub mod unity_auth;
+pub mod unity_request;
+pub mod unity_error;
+pub mod cloud_save;
+pub mod http_cloud_save;
+pub mod memory_cloud_save;
+pub mod directory_cloud_save;
+pub mod environments;
\ No newline at end of file
@@ -5,7 +5,26 @@ use unity_discordbot::{config::load_config, db::Db};
 async fn main() {
     load_config();
     let db = Db::new().await;
-    db.create_tables_if_needed().await.unwrap();
+
+    let args: Vec<String> = std::env::args().collect();
+    if args.iter().any(|arg| arg == "--migration-status") {
+        for status in db.migration_status().await.unwrap() {
+            let applied_at = status.applied_at.unwrap_or_else(|| "pending".to_string());
+            println!("{:>4}  {:<32}  {}", status.version, applied_at, status.description);
+        }
+        return;
+    } else if args.iter().any(|arg| arg == "--migrate-dry-run") {
+        let pending = db.migrate(true).await.unwrap();
+        if pending.is_empty() {
+            println!("Database schema is up to date");
+        }
+        for migration in pending {
+            println!("-- Migration {}: {}\n{}\n", migration.version, migration.description, migration.sql_for(db.backend()));
+        }
+        return;
+    }
+
+    db.migrate(false).await.unwrap();
 
     match unity_discordbot::bot::Bot::new(db) {
         Ok(bot) => {
@@ -0,0 +1,142 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use async_trait::async_trait;
+use chrono::Utc;
+use serde_json::Value;
+use crate::cloud_save::{get_local_page, validate_key, CloudSave};
+use crate::models::{AccessClass, ItemTimestamp, PageLinks, PlayerFile, PlayerFilesPage, PlayerItem, PlayerItemsPage};
+use crate::unity_error::UnityError;
+use crate::Error;
+
+#[derive(Clone)]
+struct StoredFile {
+    file: PlayerFile,
+    content: Vec<u8>,
+}
+
+#[derive(Default)]
+struct MemoryData {
+    custom_items: HashMap<String, HashMap<String, PlayerItem>>,
+    player_items: HashMap<(String, AccessClass), HashMap<String, PlayerItem>>,
+    player_files: HashMap<String, HashMap<String, StoredFile>>,
+    writes: u64,
+}
+
+impl MemoryData {
+    fn next_write_lock(&mut self) -> String {
+        self.writes += 1;
+        self.writes.to_string()
+    }
+}
+
+// Keeps everything in process, for tests and trying the bot out without Unity. Data is lost on restart.
+#[derive(Default)]
+pub struct MemoryCloudSave {
+    data: Mutex<MemoryData>,
+}
+
+impl MemoryCloudSave {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+fn set_item(items: &mut HashMap<String, PlayerItem>, key: &str, value: Value, write_lock: String) {
+    let now = ItemTimestamp { date: Utc::now().to_rfc3339() };
+    let created = items.get(key).and_then(|item| item.created.clone()).unwrap_or_else(|| now.clone());
+    items.insert(key.to_string(), PlayerItem {
+        key: key.to_string(),
+        value,
+        write_lock: Some(write_lock),
+        modified: Some(now),
+        created: Some(created),
+    });
+}
+
+#[async_trait]
+impl CloudSave for MemoryCloudSave {
+    async fn get_custom_items(&self, custom_id: &str, keys: Option<&str>) -> Result<Vec<PlayerItem>, Error> {
+        let data = self.data.lock().unwrap();
+        let items = data.custom_items.get(custom_id)
+            .map(|items| items.iter().map(|(key, item)| (key.clone(), item.clone())).collect())
+            .unwrap_or_default();
+        Ok(get_local_page(items, keys, None))
+    }
+
+    async fn set_custom_item(&self, custom_id: &str, key: &str, value: Value) -> Result<(), Error> {
+        validate_key(key)?;
+        let mut data = self.data.lock().unwrap();
+        let write_lock = data.next_write_lock();
+        set_item(data.custom_items.entry(custom_id.to_string()).or_default(), key, value, write_lock);
+        Ok(())
+    }
+
+    async fn delete_custom_item(&self, custom_id: &str, key: &str) -> Result<(), Error> {
+        let mut data = self.data.lock().unwrap();
+        match data.custom_items.get_mut(custom_id).and_then(|items| items.remove(key)) {
+            Some(_) => Ok(()),
+            None => Err(UnityError::not_found(&format!("Failed to delete {} item {}", custom_id, key), &format!("memory://custom/{}/items/{}", custom_id, key)).into()),
+        }
+    }
+
+    async fn get_player_items_page(&self, player_id: &str, keys: Option<&str>, after: Option<&str>, access_class: AccessClass) -> Result<PlayerItemsPage, Error> {
+        let data = self.data.lock().unwrap();
+        let items = data.player_items.get(&(player_id.to_string(), access_class))
+            .map(|items| items.iter().map(|(key, item)| (key.clone(), item.clone())).collect())
+            .unwrap_or_default();
+        Ok(PlayerItemsPage {
+            results: get_local_page(items, keys, after),
+            links: PageLinks::default(),
+        })
+    }
+
+    async fn set_player_item(&self, player_id: &str, key: &str, value: Value, access_class: AccessClass) -> Result<(), Error> {
+        validate_key(key)?;
+        let mut data = self.data.lock().unwrap();
+        let write_lock = data.next_write_lock();
+        set_item(data.player_items.entry((player_id.to_string(), access_class)).or_default(), key, value, write_lock);
+        Ok(())
+    }
+
+    async fn get_player_files_page(&self, player_id: &str, after: Option<&str>) -> Result<PlayerFilesPage, Error> {
+        let data = self.data.lock().unwrap();
+        let files = data.player_files.get(player_id)
+            .map(|files| files.iter().map(|(key, stored)| (key.clone(), stored.file.clone())).collect())
+            .unwrap_or_default();
+        Ok(PlayerFilesPage {
+            results: get_local_page(files, None, after),
+            links: PageLinks::default(),
+        })
+    }
+
+    async fn download_player_file(&self, player_id: &str, key: &str) -> Result<Vec<u8>, Error> {
+        let data = self.data.lock().unwrap();
+        match data.player_files.get(player_id).and_then(|files| files.get(key)) {
+            Some(stored) => Ok(stored.content.clone()),
+            None => Err(UnityError::not_found(&format!("Failed to get player file {}", key), &format!("memory://players/{}/files/{}", player_id, key)).into()),
+        }
+    }
+
+    async fn upload_player_file(&self, player_id: &str, key: &str, content: Vec<u8>, content_type: &str) -> Result<(), Error> {
+        validate_key(key)?;
+        let mut data = self.data.lock().unwrap();
+        let write_lock = data.next_write_lock();
+        let file = PlayerFile {
+            key: key.to_string(),
+            size: content.len() as u64,
+            content_type: Some(content_type.to_string()),
+            write_lock: Some(write_lock),
+            modified: Some(ItemTimestamp { date: Utc::now().to_rfc3339() }),
+        };
+        data.player_files.entry(player_id.to_string()).or_default().insert(key.to_string(), StoredFile { file, content });
+        Ok(())
+    }
+
+    async fn delete_player_file(&self, player_id: &str, key: &str) -> Result<(), Error> {
+        let mut data = self.data.lock().unwrap();
+        match data.player_files.get_mut(player_id).and_then(|files| files.remove(key)) {
+            Some(_) => Ok(()),
+            None => Err(UnityError::not_found(&format!("Failed to delete player file {}", key), &format!("memory://players/{}/files/{}", player_id, key)).into()),
+        }
+    }
+}
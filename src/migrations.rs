@@ -0,0 +1,243 @@
+use chrono::Utc;
+use sqlx::{AnyPool, Executor};
+use crate::db::DbBackend;
+use crate::Error;
+
+pub struct Migration {
+    pub version: i64,
+    pub description: &'static str,
+    pub sql: &'static str,
+    // Replaces `sql` on Postgres, for migrations shipped before Postgres was supported.
+    pub postgres_sql: Option<&'static str>,
+}
+
+pub struct MigrationStatus {
+    pub version: i64,
+    pub description: &'static str,
+    pub applied_at: Option<String>,
+}
+
+// Applied in order on startup against both SQLite and Postgres, so stick to SQL both accept (BIGINT for Discord IDs)
+// and declare auto-incrementing keys as `id AUTO_ID`, see `Migration::sql_for`. Migrations 1 and 2 predate Postgres
+// support and keep their SQLite SQL, with a Postgres version in `postgres_sql`.
+// Never change what a shipped migration does, add a new one instead.
+pub const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        description: "Create user_gift_codes and blocked_users",
+        sql: "CREATE TABLE IF NOT EXISTS user_gift_codes (
+            user_id INTEGER NOT NULL,
+            gift_code_key TEXT NOT NULL,
+            PRIMARY KEY(user_id, gift_code_key)
+        );
+        CREATE TABLE IF NOT EXISTS blocked_users (
+            user_id INTEGER NOT NULL PRIMARY KEY,
+            reason TEXT,
+            expires_at TEXT,
+            blocked_by INTEGER NOT NULL,
+            blocked_at TEXT NOT NULL
+        );",
+        postgres_sql: Some("CREATE TABLE IF NOT EXISTS user_gift_codes (
+            user_id BIGINT NOT NULL,
+            gift_code_key TEXT NOT NULL,
+            PRIMARY KEY(user_id, gift_code_key)
+        );
+        CREATE TABLE IF NOT EXISTS blocked_users (
+            user_id BIGINT NOT NULL PRIMARY KEY,
+            reason TEXT,
+            expires_at TEXT,
+            blocked_by BIGINT NOT NULL,
+            blocked_at TEXT NOT NULL
+        );"),
+    },
+    Migration {
+        version: 2,
+        description: "Store redemption details with integer user IDs",
+        sql: "CREATE TABLE user_gift_codes_v2 (
+            user_id INTEGER NOT NULL,
+            gift_code_key TEXT NOT NULL,
+            username TEXT,
+            guild_id INTEGER,
+            channel_id INTEGER,
+            remaining_amount INTEGER,
+            redeemed_at TEXT,
+            PRIMARY KEY(user_id, gift_code_key)
+        );
+        INSERT OR IGNORE INTO user_gift_codes_v2 (user_id, gift_code_key)
+            SELECT CAST(user_id AS INTEGER), gift_code_key FROM user_gift_codes;
+        DROP TABLE user_gift_codes;
+        ALTER TABLE user_gift_codes_v2 RENAME TO user_gift_codes;",
+        postgres_sql: Some("CREATE TABLE user_gift_codes_v2 (
+            user_id BIGINT NOT NULL,
+            gift_code_key TEXT NOT NULL,
+            username TEXT,
+            guild_id BIGINT,
+            channel_id BIGINT,
+            remaining_amount BIGINT,
+            redeemed_at TEXT,
+            PRIMARY KEY(user_id, gift_code_key)
+        );
+        INSERT INTO user_gift_codes_v2 (user_id, gift_code_key)
+            SELECT user_id, gift_code_key FROM user_gift_codes ON CONFLICT DO NOTHING;
+        DROP TABLE user_gift_codes;
+        ALTER TABLE user_gift_codes_v2 RENAME TO user_gift_codes;"),
+    },
+    Migration {
+        version: 3,
+        description: "Track the initial amount of each gift code",
+        sql: "CREATE TABLE gift_codes (
+            gift_code_key TEXT NOT NULL PRIMARY KEY,
+            initial_amount BIGINT NOT NULL,
+            created_at TEXT NOT NULL
+        );",
+        postgres_sql: None,
+    },
+    Migration {
+        version: 4,
+        description: "Snapshot save data before every write",
+        sql: "CREATE TABLE save_snapshots (
+            id AUTO_ID,
+            player_id TEXT NOT NULL,
+            save_data TEXT NOT NULL,
+            command TEXT NOT NULL,
+            actor_id BIGINT NOT NULL,
+            created_at TEXT NOT NULL
+        );
+        CREATE INDEX save_snapshots_player_id ON save_snapshots (player_id);",
+        postgres_sql: None,
+    },
+    Migration {
+        version: 5,
+        description: "Audit rewards granted directly to players",
+        sql: "CREATE TABLE grants (
+            id AUTO_ID,
+            player_id TEXT NOT NULL,
+            rewards TEXT NOT NULL,
+            reason TEXT NOT NULL,
+            snapshot_id BIGINT NOT NULL,
+            actor_id BIGINT NOT NULL,
+            created_at TEXT NOT NULL
+        );
+        CREATE INDEX grants_player_id ON grants (player_id);",
+        postgres_sql: None,
+    },
+    Migration {
+        version: 6,
+        description: "Store commands waiting for a second owner's approval",
+        sql: "CREATE TABLE pending_approvals (
+            id AUTO_ID,
+            command TEXT NOT NULL,
+            params TEXT NOT NULL,
+            requested_by BIGINT NOT NULL,
+            channel_id BIGINT NOT NULL,
+            status TEXT NOT NULL,
+            decided_by BIGINT,
+            created_at TEXT NOT NULL,
+            expires_at TEXT NOT NULL,
+            decided_at TEXT
+        );",
+        postgres_sql: None,
+    },
+    Migration {
+        version: 7,
+        description: "Record the Unity environment of save snapshots",
+        sql: "ALTER TABLE save_snapshots ADD COLUMN environment TEXT NOT NULL DEFAULT 'production';",
+        postgres_sql: None,
+    },
+    Migration {
+        version: 8,
+        description: "Count redemptions revoked without returning the unit to the pool",
+        sql: "ALTER TABLE gift_codes ADD COLUMN revoked_without_return BIGINT NOT NULL DEFAULT 0;",
+        postgres_sql: None,
+    },
+    Migration {
+        version: 9,
+        description: "Store the outcome of approved commands",
+        sql: "ALTER TABLE pending_approvals ADD COLUMN outcome TEXT;",
+        postgres_sql: None,
+    },
+    Migration {
+        version: 10,
+        description: "Record the Unity environment of grants",
+        sql: "ALTER TABLE grants ADD COLUMN environment TEXT NOT NULL DEFAULT 'production';",
+        postgres_sql: None,
+    },
+];
+
+impl Migration {
+    pub fn sql_for(&self, backend: &dyn DbBackend) -> String {
+        backend.migration_sql(self).replace("AUTO_ID", backend.auto_id())
+    }
+}
+
+pub async fn status(pool: &AnyPool, backend: &dyn DbBackend) -> Result<Vec<MigrationStatus>, Error> {
+    let applied: Vec<(i64, String)> = if has_schema_version_table(pool, backend).await? {
+        sqlx::query_as("SELECT version, applied_at FROM schema_version")
+            .fetch_all(pool).await?
+    } else {
+        Vec::new()
+    };
+
+    Ok(MIGRATIONS.iter().map(|migration| MigrationStatus {
+        version: migration.version,
+        description: migration.description,
+        applied_at: applied.iter()
+            .find(|(version, _)| *version == migration.version)
+            .map(|(_, applied_at)| applied_at.clone()),
+    }).collect())
+}
+
+pub async fn pending(pool: &AnyPool, backend: &dyn DbBackend) -> Result<Vec<&'static Migration>, Error> {
+    let current_version = current_version(pool, backend).await?;
+    Ok(MIGRATIONS.iter().filter(|migration| migration.version > current_version).collect())
+}
+
+pub async fn apply(pool: &AnyPool, backend: &dyn DbBackend, dry_run: bool) -> Result<Vec<&'static Migration>, Error> {
+    let pending = pending(pool, backend).await?;
+    if dry_run {
+        return Ok(pending);
+    }
+
+    create_schema_version_table(pool).await?;
+    for migration in &pending {
+        let mut tx = pool.begin().await?;
+        tx.execute(migration.sql_for(backend).as_str()).await?;
+        sqlx::query("INSERT INTO schema_version (version, description, applied_at) VALUES ($1, $2, $3)")
+            .bind(migration.version)
+            .bind(migration.description)
+            .bind(Utc::now().to_rfc3339())
+            .execute(&mut *tx).await?;
+        tx.commit().await?;
+        println!("Applied database migration {}: {}", migration.version, migration.description);
+    }
+
+    Ok(pending)
+}
+
+// A database without schema_version has no migrations applied yet. Reading the version must not create it,
+// so status and dry runs leave the database untouched.
+pub async fn current_version(pool: &AnyPool, backend: &dyn DbBackend) -> Result<i64, Error> {
+    if !has_schema_version_table(pool, backend).await? {
+        return Ok(0);
+    }
+
+    let (version,): (i64,) = sqlx::query_as("SELECT COALESCE(MAX(version), 0) FROM schema_version")
+        .fetch_one(pool).await?;
+    Ok(version)
+}
+
+async fn has_schema_version_table(pool: &AnyPool, backend: &dyn DbBackend) -> Result<bool, Error> {
+    let (count,): (i64,) = sqlx::query_as(backend.schema_version_table_query()).fetch_one(pool).await?;
+    Ok(count > 0)
+}
+
+async fn create_schema_version_table(pool: &AnyPool) -> Result<(), Error> {
+    sqlx::query(
+        "CREATE TABLE IF NOT EXISTS schema_version (
+            version BIGINT NOT NULL PRIMARY KEY,
+            description TEXT NOT NULL,
+            applied_at TEXT NOT NULL
+        )"
+    ).execute(pool).await?;
+    Ok(())
+}
@@ -1,4 +1,5 @@
 use std::{fmt, str::FromStr};
+use chrono::{DateTime, Utc};
 use crate::Error;
 use serde::{de::Error as SerdeError, Deserialize, Deserializer, Serialize};
 use serde_json::Value;
@@ -50,13 +51,6 @@ pub struct SaveValueRequest {
     pub value: Value,
 }
 
-#[derive(Debug, Serialize)]
-#[serde(rename_all = "camelCase")]
-pub struct SaveStringRequest {
-    pub key: String,
-    pub value: String,
-}
-
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct GiftCodeResponse {
@@ -100,15 +94,247 @@ impl FromStr for GamePlatform {
     }
 }
 
+// Cloud Save access classes for player data. Default is what the game reads and writes, public is
+// readable by other players, protected is server-written only and private is never sent to clients.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum AccessClass {
+    Default,
+    Public,
+    Protected,
+    Private,
+}
+
+impl AccessClass {
+    pub fn items_path(&self) -> &'static str {
+        match self {
+            AccessClass::Default => "items",
+            AccessClass::Public => "public/items",
+            AccessClass::Protected => "protected/items",
+            AccessClass::Private => "private/items",
+        }
+    }
+}
+
+impl fmt::Display for AccessClass {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AccessClass::Default => write!(f, "default"),
+            AccessClass::Public => write!(f, "public"),
+            AccessClass::Protected => write!(f, "protected"),
+            AccessClass::Private => write!(f, "private"),
+        }
+    }
+}
+
+impl FromStr for AccessClass {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "default" => Ok(AccessClass::Default),
+            "public" => Ok(AccessClass::Public),
+            "protected" => Ok(AccessClass::Protected),
+            "private" => Ok(AccessClass::Private),
+            _ => Err(format!("Invalid access class: {}. Use default, public, protected or private", s).into()),
+        }
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct GetAllGiftCodesResponse {
     pub results: Vec<GiftCodeResponse>,
 }
 
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct ItemTimestamp {
+    pub date: String,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PlayerItem {
+    pub key: String,
+    pub value: Value,
+    #[serde(default)]
+    pub write_lock: Option<String>,
+    #[serde(default)]
+    pub modified: Option<ItemTimestamp>,
+    #[serde(default)]
+    pub created: Option<ItemTimestamp>,
+}
+
+impl PlayerItem {
+    // Size of the stored value as the game wrote it; string values are measured without the JSON quotes.
+    pub fn size(&self) -> usize {
+        match &self.value {
+            Value::String(s) => s.len(),
+            value => value.to_string().len(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct PageLinks {
+    #[serde(default)]
+    pub next: Option<String>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct PlayerItemsPage {
+    pub results: Vec<PlayerItem>,
+    #[serde(default)]
+    pub links: PageLinks,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PlayerFile {
+    pub key: String,
+    pub size: u64,
+    #[serde(default)]
+    pub content_type: Option<String>,
+    #[serde(default)]
+    pub write_lock: Option<String>,
+    #[serde(default)]
+    pub modified: Option<ItemTimestamp>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct PlayerFilesPage {
+    pub results: Vec<PlayerFile>,
+    #[serde(default)]
+    pub links: PageLinks,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FileUploadRequest {
+    pub content_type: String,
+    pub content_length: usize,
+    pub content_md5: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SignedUrlResponse {
+    pub signed_url: String,
+}
+
 #[derive(Clone, Serialize, Deserialize, Debug)]
 #[serde(rename_all = "camelCase")]
 pub struct GameVersion {
     pub version_number: String,
     pub force_update: bool,
 }
+
+#[derive(Clone, Debug)]
+pub struct GiftCodeRedemption {
+    pub gift_code_key: String,
+    pub user_id: u64,
+    pub username: String,
+    pub guild_id: Option<u64>,
+    pub channel_id: u64,
+    pub remaining_amount: u32,
+    pub redeemed_at: String,
+}
+
+#[derive(Clone, Debug)]
+pub struct SaveDataWrite {
+    pub command: String,
+    pub actor_id: u64,
+    pub skip_validation: bool,
+}
+
+impl SaveDataWrite {
+    pub fn new(command: &str, actor_id: u64, skip_validation: bool) -> Self {
+        Self {
+            command: command.to_string(),
+            actor_id,
+            skip_validation,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct SaveSnapshot {
+    pub id: i64,
+    pub player_id: String,
+    pub environment: String,
+    pub save_data: String,
+    pub command: String,
+    pub actor_id: u64,
+    pub created_at: String,
+}
+
+pub enum SubscriptionChange {
+    Extend(i32),
+    Set(DateTime<Utc>),
+    Revoke,
+}
+
+#[derive(Clone, Debug)]
+pub struct GrantRecord {
+    pub id: i64,
+    pub player_id: String,
+    pub environment: String,
+    pub rewards: String,
+    pub reason: String,
+    pub snapshot_id: i64,
+    pub actor_id: u64,
+    pub created_at: String,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ApprovalStatus {
+    Pending,
+    Approved,
+    Rejected,
+    Expired,
+    // Approved requests move on to one of these once the command has run.
+    Executed,
+    Failed,
+}
+
+impl fmt::Display for ApprovalStatus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ApprovalStatus::Pending => write!(f, "pending"),
+            ApprovalStatus::Approved => write!(f, "approved"),
+            ApprovalStatus::Rejected => write!(f, "rejected"),
+            ApprovalStatus::Expired => write!(f, "expired"),
+            ApprovalStatus::Executed => write!(f, "executed"),
+            ApprovalStatus::Failed => write!(f, "failed"),
+        }
+    }
+}
+
+impl FromStr for ApprovalStatus {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "pending" => Ok(ApprovalStatus::Pending),
+            "approved" => Ok(ApprovalStatus::Approved),
+            "rejected" => Ok(ApprovalStatus::Rejected),
+            "expired" => Ok(ApprovalStatus::Expired),
+            "executed" => Ok(ApprovalStatus::Executed),
+            "failed" => Ok(ApprovalStatus::Failed),
+            _ => Err(format!("Invalid approval status: {}", s).into()),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct ApprovalRequest {
+    pub id: i64,
+    pub command: String,
+    pub params: String,
+    pub requested_by: u64,
+    pub channel_id: u64,
+    pub status: ApprovalStatus,
+    pub decided_by: Option<u64>,
+    pub created_at: String,
+    pub expires_at: String,
+    pub outcome: Option<String>,
+}
@@ -0,0 +1,81 @@
+use chrono::{DateTime, Utc};
+use poise::serenity_prelude::CreateEmbed;
+use serde_json::Value;
+use crate::config::PlayerSummaryConfig;
+use crate::constans::{EMBED_DESCRIPTION_LIMIT, EMBED_FIELD_LIMIT, EMBED_FIELD_VALUE_LIMIT};
+use crate::json_diff::truncate;
+
+fn format_summary_value(value: Option<&Value>) -> String {
+    match value {
+        None | Some(Value::Null) => "-".to_string(),
+        Some(Value::String(s)) => s.clone(),
+        // Currencies and similar maps read better one entry per line.
+        Some(Value::Object(map)) if !map.is_empty() => map.iter()
+            .map(|(key, value)| format!("{}: {}", key, format_summary_value(Some(value))))
+            .collect::<Vec<String>>()
+            .join("\n"),
+        Some(value) => value.to_string(),
+    }
+}
+
+pub fn format_subscription(product: &Value) -> String {
+    let Some(expires_at) = product.get("expiresAt").and_then(|v| v.as_str()) else {
+        return "No expiry set".to_string();
+    };
+    let Ok(expires_at) = DateTime::parse_from_rfc3339(expires_at) else {
+        return format!("Unreadable expiry: {}", expires_at);
+    };
+
+    let expires_at = expires_at.with_timezone(&Utc);
+    let friendly_date = expires_at.format("%B %d, %Y %H:%M UTC").to_string();
+    let remaining = expires_at - Utc::now();
+    if remaining.num_seconds() > 0 {
+        format!("{} days left (until {})", remaining.num_days(), friendly_date)
+    } else {
+        format!("Expired on {}", friendly_date)
+    }
+}
+
+// Discord rejects empty field values and ones over 1024 characters, which a big inventory easily reaches.
+fn to_field_value(value: String) -> String {
+    if value.is_empty() {
+        "-".to_string()
+    } else {
+        truncate(value, EMBED_FIELD_VALUE_LIMIT - 1)
+    }
+}
+
+// Discord allows 25 fields per embed, so whatever doesn't fit is listed in the description instead.
+pub fn get_player_summary_embed(player_id: &str, save_data: &Value, config: &PlayerSummaryConfig) -> CreateEmbed {
+    let mut fields: Vec<(String, String, bool)> = config.fields.iter()
+        .map(|field| (field.label.clone(), format_summary_value(save_data.pointer(&field.pointer)), true))
+        .collect();
+
+    fields.push(("➖➖➖➖➖".to_string(), String::new(), false));
+
+    match save_data.pointer(&config.subscriptions_pointer).and_then(|v| v.as_object()) {
+        Some(subscriptions) if !subscriptions.is_empty() => {
+            for (product_id, product) in subscriptions {
+                fields.push((product_id.clone(), format_subscription(product), false));
+            }
+        },
+        _ => {
+            fields.push(("Subscriptions".to_string(), "None".to_string(), false));
+        }
+    }
+
+    let mut description = format!("playerId: {}", player_id);
+    if fields.len() > EMBED_FIELD_LIMIT {
+        let overflow = fields.split_off(EMBED_FIELD_LIMIT);
+        description.push('\n');
+        for (name, value, _) in overflow {
+            description.push_str(&format!("\n**{}**: {}", name, value.replace('\n', ", ")));
+        }
+    }
+
+    let fields = fields.into_iter().map(|(name, value, inline)| (name, to_field_value(value), inline));
+    CreateEmbed::default()
+        .title("Player Summary")
+        .description(truncate(description, EMBED_DESCRIPTION_LIMIT - 1))
+        .fields(fields)
+}
@@ -0,0 +1,119 @@
+use std::fmt;
+use std::str::FromStr;
+use crate::db::Db;
+use crate::unity_service::UnityService;
+use crate::Error;
+
+pub enum ReconcileRepair {
+    Cloud,
+    Db,
+}
+
+impl FromStr for ReconcileRepair {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "cloud" => Ok(ReconcileRepair::Cloud),
+            "db" => Ok(ReconcileRepair::Db),
+            _ => Err(format!("Invalid repair target: {}. Use cloud or db", s).into()),
+        }
+    }
+}
+
+pub struct GiftCodeReconciliation {
+    pub gift_code_key: String,
+    pub initial_amount: Option<u32>,
+    pub cloud_amount: u32,
+    pub redemptions: u32,
+    pub revoked_without_return: u32,
+}
+
+impl GiftCodeReconciliation {
+    pub fn expected_cloud_amount(&self) -> Option<u32> {
+        self.initial_amount.map(|initial_amount| initial_amount.saturating_sub(self.redemptions + self.revoked_without_return))
+    }
+
+    pub fn has_drift(&self) -> bool {
+        self.expected_cloud_amount().is_some_and(|expected| expected != self.cloud_amount)
+    }
+}
+
+impl fmt::Display for GiftCodeReconciliation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match (self.initial_amount, self.expected_cloud_amount()) {
+            (Some(initial_amount), Some(expected)) => write!(
+                f,
+                "Code: {}, Initial: {}, Redemptions: {}, Revoked: {}, Cloud Save amount: {}, Expected: {}",
+                self.gift_code_key, initial_amount, self.redemptions, self.revoked_without_return, self.cloud_amount, expected
+            ),
+            _ => write!(
+                f,
+                "Code: {}, Initial: untracked, Redemptions: {}, Cloud Save amount: {}",
+                self.gift_code_key, self.redemptions, self.cloud_amount
+            ),
+        }
+    }
+}
+
+pub async fn reconcile_gift_codes(unity_service: &UnityService, db: &Db) -> Result<Vec<GiftCodeReconciliation>, Error> {
+    let gift_codes = unity_service.get_all_gift_codes().await?;
+    let mut reconciliations = Vec::with_capacity(gift_codes.results.len());
+
+    for gift_code in gift_codes.results {
+        reconciliations.push(GiftCodeReconciliation {
+            initial_amount: db.get_gift_code_initial_amount_in_db(&gift_code.key).await?,
+            cloud_amount: gift_code.value.amount,
+            redemptions: db.count_gift_code_redemptions_in_db(&gift_code.key).await?,
+            revoked_without_return: db.count_gift_code_revocations_in_db(&gift_code.key).await?,
+            gift_code_key: gift_code.key,
+        });
+    }
+
+    Ok(reconciliations)
+}
+
+// Returns false when a cloud repair was skipped. Overwriting the amount would erase a redemption that landed
+// after the reconciliation was read, so the code is left alone if its amount or redemptions moved since.
+pub async fn repair_gift_code(unity_service: &UnityService, db: &Db, reconciliation: &GiftCodeReconciliation, repair: &ReconcileRepair) -> Result<bool, Error> {
+    match repair {
+        ReconcileRepair::Cloud => {
+            let Some(expected) = reconciliation.expected_cloud_amount() else {
+                return Err(format!("Initial amount of {} is not tracked, repair the db side instead", reconciliation.gift_code_key).into());
+            };
+            let mut gift_code = unity_service.get_gift_code(reconciliation.gift_code_key.clone()).await?;
+            let redemptions = db.count_gift_code_redemptions_in_db(&reconciliation.gift_code_key).await?;
+            if gift_code.amount != reconciliation.cloud_amount || redemptions != reconciliation.redemptions {
+                return Ok(false);
+            }
+            gift_code.amount = expected;
+            unity_service.save_gift_code(&reconciliation.gift_code_key, &gift_code).await?;
+        },
+        ReconcileRepair::Db => {
+            let initial_amount = reconciliation.cloud_amount + reconciliation.redemptions + reconciliation.revoked_without_return;
+            db.record_gift_code_in_db(&reconciliation.gift_code_key, initial_amount).await?;
+        },
+    }
+    Ok(true)
+}
+
+pub fn get_reconcile_report(reconciliations: &[GiftCodeReconciliation]) -> String {
+    let drifted: Vec<String> = reconciliations.iter()
+        .filter(|r| r.has_drift())
+        .map(|r| r.to_string())
+        .collect();
+    let untracked: Vec<String> = reconciliations.iter()
+        .filter(|r| r.initial_amount.is_none())
+        .map(|r| r.to_string())
+        .collect();
+
+    let mut report = if drifted.is_empty() {
+        format!("No drift found across {} gift codes.", reconciliations.len())
+    } else {
+        format!("Drift found in {} of {} gift codes:\n{}", drifted.len(), reconciliations.len(), drifted.join("\n"))
+    };
+    if !untracked.is_empty() {
+        report.push_str(&format!("\nInitial amount not tracked:\n{}", untracked.join("\n")));
+    }
+    report
+}
@@ -0,0 +1,213 @@
+use anyhow::anyhow;
+use chrono::{DateTime, Duration, Utc};
+use json_patch::Patch;
+use serde_json::{Map, Value};
+use crate::config::GrantRewardsConfig;
+use crate::models::GiftCodeReward;
+use crate::Error;
+
+pub fn get_save_count(save_data: &Value) -> Result<u64, Error> {
+    let player_progress_data = save_data
+        .get("playerProgressData")
+        .and_then(|v| v.as_object())
+        .ok_or_else(|| anyhow!("'playerProgressData' not found or null"))?;
+
+    let save_count = player_progress_data
+        .get("saveCount")
+        .and_then(|v| v.as_u64())
+        .ok_or_else(|| anyhow!("Unable to get or cast 'saveCount' as number"))?;
+
+    Ok(save_count)
+}
+
+// A player without a save yet (null) starts from save count 0, so a whole save can be written to them.
+pub fn get_current_save_count(save_data: &Value) -> Result<u64, Error> {
+    if save_data.is_null() {
+        Ok(0)
+    } else {
+        get_save_count(save_data)
+    }
+}
+
+pub fn set_save_count(save_data: &mut Value, save_count: u64) -> Result<(), Error> {
+    let player_progress_data = save_data
+        .get_mut("playerProgressData")
+        .and_then(|v| v.as_object_mut())
+        .ok_or_else(|| anyhow!("'playerProgressData' not found or null"))?;
+
+    player_progress_data.insert("saveCount".to_string(), Value::Number(save_count.into()));
+    Ok(())
+}
+
+pub fn increase_save_count(save_data: &mut Value, increase_save_count_by: u64) -> Result<(), Error> {
+    if increase_save_count_by < 1 {
+        return Err(anyhow!("Increase save count by must be greater than 0").into());
+    }
+
+    let save_count = get_save_count(save_data)?;
+    set_save_count(save_data, save_count + increase_save_count_by)
+}
+
+fn split_pointer(pointer: &str) -> Result<(&str, String), Error> {
+    if !pointer.starts_with('/') {
+        return Err(anyhow!("Invalid JSON pointer: {}. It must start with '/'", pointer).into());
+    }
+
+    let (parent, token) = pointer.rsplit_once('/').unwrap();
+    Ok((parent, token.replace("~1", "/").replace("~0", "~")))
+}
+
+fn parse_array_index(token: &str, len: usize, allow_end: bool) -> Result<usize, Error> {
+    if allow_end && token == "-" {
+        return Ok(len);
+    }
+
+    let index = token.parse::<usize>().map_err(|_| anyhow!("Invalid array index: {}", token))?;
+    let in_bounds = if allow_end { index <= len } else { index < len };
+    if !in_bounds {
+        return Err(anyhow!("Array index {} out of bounds", index).into());
+    }
+    Ok(index)
+}
+
+// Sets the value at the JSON pointer and returns the value it replaced. The parent must exist;
+// array targets are replaced by index, and "-" appends.
+pub fn set_value_at_pointer(save_data: &mut Value, pointer: &str, value: Value) -> Result<Option<Value>, Error> {
+    let (parent_pointer, token) = split_pointer(pointer)?;
+    let parent = save_data
+        .pointer_mut(parent_pointer)
+        .ok_or_else(|| anyhow!("'{}' not found", parent_pointer))?;
+
+    match parent {
+        Value::Object(map) => Ok(map.insert(token, value)),
+        Value::Array(array) => {
+            let index = parse_array_index(&token, array.len(), true)?;
+            if index == array.len() {
+                array.push(value);
+                Ok(None)
+            } else {
+                Ok(Some(std::mem::replace(&mut array[index], value)))
+            }
+        },
+        _ => Err(anyhow!("'{}' is not an object or array", parent_pointer).into()),
+    }
+}
+
+pub fn remove_value_at_pointer(save_data: &mut Value, pointer: &str) -> Result<Value, Error> {
+    let (parent_pointer, token) = split_pointer(pointer)?;
+    let parent = save_data
+        .pointer_mut(parent_pointer)
+        .ok_or_else(|| anyhow!("'{}' not found", parent_pointer))?;
+
+    match parent {
+        Value::Object(map) => map.remove(&token).ok_or_else(|| anyhow!("'{}' not found", pointer).into()),
+        Value::Array(array) => {
+            let index = parse_array_index(&token, array.len(), false)?;
+            Ok(array.remove(index))
+        },
+        _ => Err(anyhow!("'{}' is not an object or array", parent_pointer).into()),
+    }
+}
+
+// Applies an RFC 6902 patch to a copy of the save. `test` operations act as preconditions and any
+// failing operation rejects the whole patch.
+pub fn apply_save_data_patch(save_data: &Value, patch: Value) -> Result<Value, Error> {
+    let patch: Patch = serde_json::from_value(patch).map_err(|e| anyhow!("Invalid JSON Patch: {}", e))?;
+    let mut patched_save_data = save_data.clone();
+    json_patch::patch(&mut patched_save_data, &patch).map_err(|e| anyhow!("Failed to apply JSON Patch: {}", e))?;
+    Ok(patched_save_data)
+}
+
+fn get_subscriptions_mut(save_data: &mut Value) -> Result<&mut Map<String, Value>, Error> {
+    let player_account_data = save_data
+        .get_mut("playerAccountData")
+        .and_then(|v| v.as_object_mut())
+        .ok_or_else(|| anyhow!("'playerAccountData' not found or null"))?;
+
+    let shop_data = player_account_data
+        .get_mut("shopData")
+        .and_then(|v| v.as_object_mut())
+        .ok_or_else(|| anyhow!("'shopData' not found or null"))?;
+
+    let shop_subscription_data = shop_data
+        .get_mut("shopSubscriptionData")
+        .and_then(|v| v.as_object_mut())
+        .ok_or_else(|| anyhow!("'shopSubscriptionData' not found or null"))?;
+
+    Ok(shop_subscription_data)
+}
+
+pub fn get_subscription_expiry(save_data: &Value, product_id: &str) -> Result<Option<DateTime<Utc>>, Error> {
+    let expires_at = save_data
+        .pointer(&format!("/playerAccountData/shopData/shopSubscriptionData/{}/expiresAt", product_id.replace('~', "~0").replace('/', "~1")))
+        .and_then(|v| v.as_str());
+
+    match expires_at {
+        Some(expires_at) => Ok(Some(DateTime::parse_from_rfc3339(expires_at)?.with_timezone(&Utc))),
+        None => Ok(None),
+    }
+}
+
+// Extensions stack on a subscription that is still running and start from now otherwise.
+pub fn get_extended_expiry(current_expires_at: Option<DateTime<Utc>>, days: i32) -> DateTime<Utc> {
+    let now = Utc::now();
+    let base = current_expires_at.filter(|expires_at| *expires_at > now).unwrap_or(now);
+    base + Duration::days(days as i64)
+}
+
+pub fn set_subscription_expiry(save_data: &mut Value, product_id: &str, expires_at: DateTime<Utc>) -> Result<(), Error> {
+    let product = get_subscriptions_mut(save_data)?
+        .entry(product_id.to_string())
+        .or_insert_with(|| Value::Object(Map::new()))
+        .as_object_mut()
+        .ok_or_else(|| anyhow!("'{}' is not an object", product_id))?;
+
+    product.insert("expiresAt".to_string(), Value::String(expires_at.to_rfc3339()));
+    Ok(())
+}
+
+fn add_to_value_at_pointer(save_data: &mut Value, pointer: &str, amount: u64) -> Result<(), Error> {
+    let current = match save_data.pointer(pointer) {
+        None | Some(Value::Null) => 0,
+        Some(value) => value.as_u64().ok_or_else(|| anyhow!("'{}' is not a number", pointer))?,
+    };
+    set_value_at_pointer(save_data, pointer, Value::Number((current + amount).into()))?;
+    Ok(())
+}
+
+// Adds the rewards to the save at the pointers from config. Currencies and XP are added to the
+// existing amounts, missing ones start from 0; items are appended without their display name.
+pub fn apply_grant(save_data: &mut Value, rewards: &GiftCodeReward, config: &GrantRewardsConfig) -> Result<(), Error> {
+    for currency in &rewards.currency_rewards {
+        let pointer = config.currency_pointer.replace("{currencyType}", &currency.currency_type.to_string());
+        add_to_value_at_pointer(save_data, &pointer, currency.currency_amount as u64)?;
+    }
+
+    if !rewards.item_rewards.is_empty() {
+        let items = save_data
+            .pointer_mut(&config.items_pointer)
+            .and_then(|v| v.as_array_mut())
+            .ok_or_else(|| anyhow!("'{}' not found or not an array", config.items_pointer))?;
+
+        for item in &rewards.item_rewards {
+            let mut item = serde_json::to_value(item)?;
+            if let Some(item) = item.as_object_mut() {
+                item.remove("name");
+            }
+            items.push(item);
+        }
+    }
+
+    if rewards.xp_reward > 0 {
+        add_to_value_at_pointer(save_data, &config.xp_pointer, rewards.xp_reward as u64)?;
+    }
+
+    Ok(())
+}
+
+// Sets the subscription to expire `duration` days from now, as /updatesubscription always has.
+pub fn update_subscription(save_data: &mut Value, product_id: &str, duration: i32, increase_save_count_by: u64) -> Result<(), Error> {
+    let expires_at = Utc::now() + Duration::days(duration as i64);
+    set_subscription_expiry(save_data, product_id, expires_at)?;
+    increase_save_count(save_data, increase_save_count_by)
+}
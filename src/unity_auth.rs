@@ -0,0 +1,93 @@
+use base64::{decode_config, encode, URL_SAFE_NO_PAD};
+use chrono::{DateTime, Duration, Utc};
+use serde::Deserialize;
+use serde_json::Value;
+use tokio::sync::RwLock;
+use crate::constans::{TOKEN_DEFAULT_LIFETIME, TOKEN_REFRESH_MARGIN};
+use crate::unity_error::UnityError;
+use crate::Error;
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TokenExchangeResponse {
+    access_token: String,
+}
+
+#[derive(Clone)]
+struct CachedToken {
+    auth_header: String,
+    expires_at: DateTime<Utc>,
+}
+
+// Exchanges the service account key for a short-lived stateless token, so the secret is only sent
+// to the token endpoint. The token is cached and refreshed shortly before it expires.
+pub struct UnityTokenProvider {
+    client: reqwest::Client,
+    token_url: String,
+    basic_auth_header: String,
+    token: RwLock<Option<CachedToken>>,
+}
+
+impl UnityTokenProvider {
+    pub fn new(client: reqwest::Client, base_url: &str, project_id: &str, environment_id: &str, key_id: &str, secret_key: &str) -> Self {
+        Self {
+            client,
+            token_url: format!("{}/auth/v1/token-exchange?projectId={}&environmentId={}", base_url, project_id, environment_id),
+            basic_auth_header: format!("Basic {}", encode(format!("{}:{}", key_id, secret_key))),
+            token: RwLock::new(None),
+        }
+    }
+
+    pub async fn get_auth_header(&self) -> Result<String, Error> {
+        let refresh_at = Utc::now() + Duration::seconds(TOKEN_REFRESH_MARGIN);
+        if let Some(token) = self.token.read().await.as_ref() {
+            if token.expires_at > refresh_at {
+                return Ok(token.auth_header.clone());
+            }
+        }
+
+        let mut token = self.token.write().await;
+        // Another request may have refreshed it while this one waited for the lock.
+        if let Some(token) = token.as_ref() {
+            if token.expires_at > refresh_at {
+                return Ok(token.auth_header.clone());
+            }
+        }
+
+        let fresh_token = self.exchange_token().await?;
+        let auth_header = fresh_token.auth_header.clone();
+        *token = Some(fresh_token);
+        Ok(auth_header)
+    }
+
+    // Drops the cached token after a 401 so the next request exchanges a new one.
+    pub async fn invalidate(&self) {
+        *self.token.write().await = None;
+    }
+
+    async fn exchange_token(&self) -> Result<CachedToken, Error> {
+        let response = self.client.post(&self.token_url)
+            .header("Authorization", &self.basic_auth_header)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(UnityError::from_response("Failed to exchange Unity service account token", response).await.into());
+        }
+
+        let token: TokenExchangeResponse = response.json().await?;
+        let expires_at = get_token_expiry(&token.access_token)
+            .unwrap_or_else(|| Utc::now() + Duration::seconds(TOKEN_DEFAULT_LIFETIME));
+        Ok(CachedToken {
+            auth_header: format!("Bearer {}", token.access_token),
+            expires_at,
+        })
+    }
+}
+
+// Reads the `exp` claim of the JWT. The token is only inspected, not verified; Unity verifies it.
+fn get_token_expiry(access_token: &str) -> Option<DateTime<Utc>> {
+    let payload = access_token.split('.').nth(1)?;
+    let claims: Value = serde_json::from_slice(&decode_config(payload, URL_SAFE_NO_PAD).ok()?).ok()?;
+    DateTime::from_timestamp(claims.get("exp")?.as_i64()?, 0)
+}
@@ -0,0 +1,111 @@
+use std::fmt;
+use reqwest::Response;
+use serde_json::Value;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum UnityErrorKind {
+    BadRequest,
+    Unauthorized,
+    Forbidden,
+    NotFound,
+    Conflict,
+    RateLimited,
+    Server,
+    Other,
+}
+
+impl UnityErrorKind {
+    pub fn from_status(status: u16) -> Self {
+        match status {
+            400 | 422 => UnityErrorKind::BadRequest,
+            401 => UnityErrorKind::Unauthorized,
+            403 => UnityErrorKind::Forbidden,
+            404 => UnityErrorKind::NotFound,
+            409 | 412 => UnityErrorKind::Conflict,
+            429 => UnityErrorKind::RateLimited,
+            500..=599 => UnityErrorKind::Server,
+            _ => UnityErrorKind::Other,
+        }
+    }
+}
+
+// A failed Unity API call. Unity answers with problem JSON (RFC 7807) carrying its own error code,
+// title and detail; bodies that aren't problem JSON end up in `detail` as is.
+#[derive(Clone, Debug)]
+pub struct UnityError {
+    pub kind: UnityErrorKind,
+    pub context: String,
+    pub status: u16,
+    pub code: Option<i64>,
+    pub title: Option<String>,
+    pub detail: Option<String>,
+    pub url: String,
+}
+
+impl UnityError {
+    pub async fn from_response(context: &str, response: Response) -> Self {
+        let status = response.status().as_u16();
+        let url = response.url().to_string();
+        let body = response.text().await.unwrap_or_default();
+        Self::from_body(context, status, &url, &body)
+    }
+
+    pub fn from_body(context: &str, status: u16, url: &str, body: &str) -> Self {
+        let problem = serde_json::from_str::<Value>(body).ok().filter(|problem| problem.is_object());
+        let field = |name: &str| problem.as_ref()
+            .and_then(|problem| problem.get(name))
+            .and_then(|value| value.as_str())
+            .map(|value| value.to_string());
+
+        let detail = match &problem {
+            Some(_) => field("detail"),
+            None if body.trim().is_empty() => None,
+            None => Some(body.trim().to_string()),
+        };
+
+        Self {
+            kind: UnityErrorKind::from_status(status),
+            context: context.to_string(),
+            status,
+            code: problem.as_ref().and_then(|problem| problem.get("code")).and_then(|code| code.as_i64()),
+            title: field("title"),
+            detail,
+            url: url.to_string(),
+        }
+    }
+
+    // For lookups that succeed but come back empty, like a gift code key that isn't stored.
+    pub fn not_found(context: &str, url: &str) -> Self {
+        Self {
+            kind: UnityErrorKind::NotFound,
+            context: context.to_string(),
+            status: 404,
+            code: None,
+            title: None,
+            detail: None,
+            url: url.to_string(),
+        }
+    }
+
+    pub fn is_retryable(&self) -> bool {
+        matches!(self.kind, UnityErrorKind::RateLimited | UnityErrorKind::Server)
+    }
+}
+
+impl fmt::Display for UnityError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.context, self.status)?;
+        if let Some(title) = &self.title {
+            write!(f, " {}", title)?;
+        }
+        if let Some(detail) = &self.detail {
+            write!(f, ": {}", detail)?;
+        }
+        if let Some(code) = self.code {
+            write!(f, " (code {})", code)?;
+        }
+        write!(f, ", request_url: {}", self.url)
+    }
+}
+
+impl std::error::Error for UnityError {}
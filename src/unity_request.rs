@@ -0,0 +1,132 @@
+use std::time::{Duration, Instant};
+use anyhow::anyhow;
+use chrono::{DateTime, Utc};
+use rand::Rng;
+use reqwest::header::{HeaderValue, AUTHORIZATION, RETRY_AFTER};
+use reqwest::{Method, RequestBuilder, Response, StatusCode};
+use tokio::sync::Mutex;
+use crate::config::UnityRequestConfig;
+use crate::unity_auth::UnityTokenProvider;
+use crate::Error;
+
+// Client-side rate limiter, so a burst of commands waits its turn instead of hitting Unity's limits.
+pub struct TokenBucket {
+    capacity: f64,
+    refill_per_second: f64,
+    state: Mutex<(f64, Instant)>,
+}
+
+impl TokenBucket {
+    pub fn new(requests: u32, window: Duration) -> Self {
+        let capacity = requests.max(1) as f64;
+        Self {
+            capacity,
+            refill_per_second: capacity / window.as_secs_f64().max(0.001),
+            state: Mutex::new((capacity, Instant::now())),
+        }
+    }
+
+    pub async fn acquire(&self) {
+        loop {
+            let wait = {
+                let mut state = self.state.lock().await;
+                let (tokens, last_refill) = *state;
+                let now = Instant::now();
+                let tokens = (tokens + now.duration_since(last_refill).as_secs_f64() * self.refill_per_second).min(self.capacity);
+                if tokens >= 1.0 {
+                    *state = (tokens - 1.0, now);
+                    return;
+                }
+                *state = (tokens, now);
+                Duration::from_secs_f64((1.0 - tokens) / self.refill_per_second)
+            };
+            tokio::time::sleep(wait).await;
+        }
+    }
+}
+
+// Full jitter: a random delay up to the exponential backoff for this attempt.
+pub fn backoff_delay(attempt: u32, initial: Duration, max: Duration) -> Duration {
+    let ceiling = initial.saturating_mul(2u32.saturating_pow(attempt)).min(max);
+    Duration::from_millis(rand::thread_rng().gen_range(0..=ceiling.as_millis() as u64))
+}
+
+// Retry-After is either a number of seconds or an HTTP date.
+pub fn parse_retry_after(value: &str) -> Option<Duration> {
+    if let Ok(seconds) = value.trim().parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let date = DateTime::parse_from_rfc2822(value.trim()).ok()?.with_timezone(&Utc);
+    Some((date - Utc::now()).to_std().unwrap_or_default())
+}
+
+// Sends every Unity request with auth, rate limiting and retries. Rate limited requests are retried
+// whatever the method since Unity didn't process them; 5xx and network errors only for idempotent ones.
+pub struct UnityRequestExecutor {
+    client: reqwest::Client,
+    auth: UnityTokenProvider,
+    rate_limiter: TokenBucket,
+    config: UnityRequestConfig,
+}
+
+impl UnityRequestExecutor {
+    pub fn new(client: reqwest::Client, auth: UnityTokenProvider, config: UnityRequestConfig) -> Self {
+        Self {
+            client,
+            auth,
+            rate_limiter: TokenBucket::new(config.rate_limit_requests, Duration::from_secs(config.rate_limit_window_seconds)),
+            config,
+        }
+    }
+
+    pub async fn send(&self, request: RequestBuilder) -> Result<Response, Error> {
+        let template = request.build()?;
+        let idempotent = matches!(*template.method(), Method::GET | Method::PUT | Method::DELETE | Method::HEAD);
+        let mut attempt = 0;
+        let mut reauthorized = false;
+
+        loop {
+            self.rate_limiter.acquire().await;
+            let mut request = template.try_clone().ok_or_else(|| anyhow!("Request to {} can't be retried", template.url()))?;
+            request.headers_mut().insert(AUTHORIZATION, HeaderValue::from_str(&self.auth.get_auth_header().await?)?);
+
+            let can_retry = attempt < self.config.max_retries;
+            let delay = match self.client.execute(request).await {
+                // A 401 usually means the token was revoked or expired early, so it's exchanged again
+                // and the request retried once.
+                Ok(response) if response.status() == StatusCode::UNAUTHORIZED && !reauthorized => {
+                    self.auth.invalidate().await;
+                    reauthorized = true;
+                    continue;
+                },
+                Ok(response) if response.status() == StatusCode::TOO_MANY_REQUESTS && can_retry => {
+                    let retry_after = response.headers().get(RETRY_AFTER)
+                        .and_then(|value| value.to_str().ok())
+                        .and_then(parse_retry_after);
+                    match retry_after {
+                        // Waiting longer than the backoff cap would hold the command up, so the 429 is returned instead.
+                        Some(delay) if delay > Duration::from_millis(self.config.max_backoff_millis) => return Ok(response),
+                        Some(delay) => delay,
+                        None => self.backoff_delay(attempt),
+                    }
+                },
+                Ok(response) if response.status().is_server_error() && idempotent && can_retry => self.backoff_delay(attempt),
+                Ok(response) => return Ok(response),
+                Err(e) if (e.is_timeout() || e.is_connect()) && idempotent && can_retry => self.backoff_delay(attempt),
+                Err(e) => return Err(e.into()),
+            };
+
+            attempt += 1;
+            println!("Retrying {} {} in {:?} (attempt {})", template.method(), template.url(), delay, attempt);
+            tokio::time::sleep(delay).await;
+        }
+    }
+
+    fn backoff_delay(&self, attempt: u32) -> Duration {
+        backoff_delay(
+            attempt,
+            Duration::from_millis(self.config.initial_backoff_millis),
+            Duration::from_millis(self.config.max_backoff_millis),
+        )
+    }
+}
@@ -1,87 +1,80 @@
 use std::env;
-use base64::encode;
+use std::sync::Arc;
 use anyhow::anyhow;
-use chrono::{Duration, Utc};
-use serde_json::Value;
-use crate::constans::{UNITY_ENVIRONMENT_ID, UNITY_KEY_ID, UNITY_PROJECT_ID, UNITY_SAVE_DATA_KEY, UNITY_SECRET_KEY};
-use crate::models::{GamePlatform, GameVersion, GetAllGiftCodesResponse, GiftCode, SaveValueRequest, SaveStringRequest};
+use jsonschema::Validator;
+use serde_json::{json, Value};
+use crate::cloud_save::{create_cloud_save, CloudSave};
+use crate::constans::{PLAYER_FILES_PAGE_LIMIT, PLAYER_ITEMS_PAGE_LIMIT, SAVE_DATA_SCHEMA_PATH, SCHEMA_VIOLATION_LIMIT, UNITY_SAVE_DATA_KEY};
+use crate::db::Db;
+use crate::config::UnityEnvironmentConfig;
+use crate::unity_error::UnityError;
+use crate::models::{AccessClass, GamePlatform, GameVersion, GetAllGiftCodesResponse, GiftCode, GiftCodeResponse, GiftCodeReward, PlayerFile, PlayerFilesPage, PlayerItem, PlayerItemsPage, SaveDataWrite};
+use crate::save_data::{get_current_save_count, set_save_count, update_subscription};
 use crate::Error;
 
+const GIFT_CODES_CUSTOM_ID: &str = "gift_codes";
+const GAME_VERSION_CUSTOM_ID: &str = "game_version";
+
 pub struct UnityService {
-    client: reqwest::Client,
-    custom_url: String,
-    players_url: String,
-    auth_header: String,
+    environment: String,
+    cloud_save: Box<dyn CloudSave>,
     save_data_key: String,
+    save_data_validator: Option<Validator>,
+    db: Arc<Db>,
 }
 
 impl UnityService {
-    pub fn new() -> Result<Self, Error> {
+    pub fn new(environment: &str, config: &UnityEnvironmentConfig, db: Arc<Db>) -> Result<Self, Error> {
+        UnityService::with_cloud_save(environment, create_cloud_save(config)?, db)
+    }
+
+    pub fn with_cloud_save(environment: &str, cloud_save: Box<dyn CloudSave>, db: Arc<Db>) -> Result<Self, Error> {
         Ok(Self {
-            client: reqwest::Client::new(),
-            custom_url: format!("{}/custom", Self::initialize_url()?),
-            players_url: format!("{}/players", Self::initialize_url()?),
-            auth_header: UnityService::initialize_auth_header()?,
+            environment: environment.to_string(),
+            cloud_save,
             save_data_key: env::var(UNITY_SAVE_DATA_KEY)?,
+            save_data_validator: UnityService::initialize_save_data_validator()?,
+            db,
         })
     }
+
+    pub fn environment(&self) -> &str {
+        &self.environment
+    }
+}
+
+// Gift codes are stored as custom items whose value is the gift code serialized to a JSON string.
+fn to_gift_code_response(item: PlayerItem) -> Result<GiftCodeResponse, Error> {
+    Ok(serde_json::from_value(json!({ "key": item.key, "value": item.value }))?)
 }
 
 impl UnityService {
-    fn initialize_url() -> Result<String, Error> {
-        let project_id = env::var(UNITY_PROJECT_ID)?;
-        let environment_id = env::var(UNITY_ENVIRONMENT_ID)?;
-
-        Ok(format!(
-            "https://services.api.unity.com/cloud-save/v1/data/projects/{}/environments/{}",
-            project_id, environment_id
-        ))
-    }
+    fn initialize_save_data_validator() -> Result<Option<Validator>, Error> {
+        let schema_path = env::var(SAVE_DATA_SCHEMA_PATH)?;
+        if schema_path.is_empty() {
+            return Ok(None);
+        }
 
-    fn initialize_auth_header() -> Result<String, Error> {
-        let key_id = env::var(UNITY_KEY_ID)?;
-        let secret_key = env::var(UNITY_SECRET_KEY)?;
-        let credentials = format!("{}:{}", key_id, secret_key);
-        let encoded_credentials = encode(credentials);
-        Ok(format!("Basic {}", encoded_credentials))
+        let schema: Value = serde_json::from_str(&std::fs::read_to_string(&schema_path)?)?;
+        let validator = jsonschema::validator_for(&schema)
+            .map_err(|e| anyhow!("Invalid save data schema {}: {}", schema_path, e))?;
+        Ok(Some(validator))
     }
-    
+
     pub async fn get_gift_code(&self, gift_code_key: String) -> Result<GiftCode, Error> {
-        let get_url = format!("{}/gift_codes/items?keys={}", self.custom_url, gift_code_key);
-        let response = self.client.get(&get_url)
-            .header("Authorization", &self.auth_header)
-            .send()
-            .await?;
-    
-        if response.status().is_success() {
-            let text = response.text().await?;
-            let gift_codes: GetAllGiftCodesResponse = serde_json::from_str(&text)?;
-            if gift_codes.results.is_empty() {
-                return Err(anyhow!("Gift code not found").into());
-            } else {
-                return Ok(gift_codes.results[0].value.clone());
-            }
-        } else {
-            let text = response.text().await?;
-            Err(anyhow!("Failed to get gift code: {}", text).into())
+        let items = self.cloud_save.get_custom_items(GIFT_CODES_CUSTOM_ID, Some(&gift_code_key)).await?;
+        match items.into_iter().find(|item| item.key == gift_code_key) {
+            Some(item) => Ok(to_gift_code_response(item)?.value),
+            None => Err(UnityError::not_found("Gift code not found", &format!("custom/{}/items/{}", GIFT_CODES_CUSTOM_ID, gift_code_key)).into()),
         }
     }
     
     pub async fn get_all_gift_codes(&self) -> Result<GetAllGiftCodesResponse, Error> {
-        let get_url = format!("{}/gift_codes/items", self.custom_url);
-    
-        let response = self.client.get(&get_url)
-            .header("Authorization", &self.auth_header)
-            .send()
-            .await?;
-    
-        if response.status().is_success() {
-            let gift_codes: GetAllGiftCodesResponse = response.json().await?;
-            Ok(gift_codes)
-        } else {
-            let text = response.text().await?;
-            Err(anyhow!("Failed to get gift codes: {}", text).into())
-        }
+        let items = self.cloud_save.get_custom_items(GIFT_CODES_CUSTOM_ID, None).await?;
+        let results = items.into_iter()
+            .map(to_gift_code_response)
+            .collect::<Result<Vec<GiftCodeResponse>, Error>>()?;
+        Ok(GetAllGiftCodesResponse { results })
     }
     
     pub async fn get_gift_code_count(&self) -> Result<u32, Error> {
@@ -90,188 +83,199 @@ impl UnityService {
         Ok(count as u32)
     }
     
-    pub async fn save_gift_code(&self, gift_code_key: &String, gift_code: &GiftCode) -> Result<(), Error> {
-        let save_url = format!("{}/gift_codes/items", self.custom_url);
-    
+    pub async fn save_gift_code(&self, gift_code_key: &str, gift_code: &GiftCode) -> Result<(), Error> {
         let serialized_data = serde_json::to_string(gift_code)?;
-        let request_body = SaveStringRequest {
-            key: gift_code_key.to_string(),
-            value: serialized_data,
-        };
-    
-        let response = self.client.post(&save_url)
-            .header("Authorization", &self.auth_header)
-            .json(&request_body)
-            .send()
-            .await?;
-    
-        if response.status().is_success() {
-            println!("Gift code saved successfully.");
-        } else {
-            let text = response.text().await?;
-            return Err(anyhow!("Failed to save gift code: {}", text).into());
-        }
-    
+        self.cloud_save.set_custom_item(GIFT_CODES_CUSTOM_ID, gift_code_key, Value::String(serialized_data)).await?;
+        println!("Gift code saved successfully.");
         Ok(())
     }
     
+    // The game version is stored as a JSON string, like gift codes.
+    pub async fn get_game_version(&self, platform: &GamePlatform) -> Result<Option<GameVersion>, Error> {
+        let platform = platform.to_string();
+        let items = self.cloud_save.get_custom_items(GAME_VERSION_CUSTOM_ID, Some(&platform)).await?;
+        let Some(item) = items.into_iter().find(|item| item.key == platform) else {
+            return Ok(None);
+        };
+        let game_version = match item.value {
+            Value::String(value) => serde_json::from_str(&value)?,
+            value => serde_json::from_value(value)?,
+        };
+        Ok(Some(game_version))
+    }
+
     pub async fn update_game_version(&self, game_version: &GameVersion, platform: GamePlatform) -> Result<(), Error> {
-        let update_url = format!("{}/game_version/items", self.custom_url);
-    
         let serialized_data = serde_json::to_string(game_version)?;
-        let request_body = SaveStringRequest {
-            key: platform.to_string(),
-            value: serialized_data,
-        };
-    
-        let response = self.client.post(&update_url)
-            .header("Authorization", &self.auth_header)
-            .json(&request_body)
-            .send()
-            .await?;
-    
-        if response.status().is_success() {
-            println!("Game version updated successfully for platform: {}", platform);
-        } else {
-            let text = response.text().await?;
-            return Err(anyhow!("Failed to update game version: {}", text).into());
-        }
-    
+        self.cloud_save.set_custom_item(GAME_VERSION_CUSTOM_ID, &platform.to_string(), Value::String(serialized_data)).await?;
+        println!("Game version updated successfully for platform: {}", platform);
         Ok(())
     }
     
     pub async fn delete_gift_code(&self, gift_code_key: &str) -> Result<(), Error> {
-        let delete_url = format!("{}/gift_codes/items/{}", self.custom_url, gift_code_key);
-    
-        let response = self.client.delete(&delete_url)
-            .header("Authorization", &self.auth_header)
-            .send()
-            .await?;
-    
-        if response.status().is_success() {
-            println!("Gift code deleted successfully.");
-        } else {
-            let text = response.text().await?;
-            return Err(anyhow!("Failed to delete gift code: {}", text).into());
-        }
+        self.cloud_save.delete_custom_item(GIFT_CODES_CUSTOM_ID, gift_code_key).await?;
+        println!("Gift code deleted successfully.");
         Ok(())
     }
 
-    pub async fn get_player_items(&self, player_id: &str, key: String) -> Result<Value, Error> {
-        let get_url = format!("{}/{}/items", self.players_url, player_id);
+    pub fn save_data_key(&self) -> &str {
+        &self.save_data_key
+    }
 
-        let mut params = vec![];
-        params.push(("keys", key));
+    // Cloud Save returns player items in pages ordered by key; `after` is the last key of the previous page.
+    pub async fn get_player_items_page(&self, player_id: &str, after: Option<&str>, access_class: AccessClass) -> Result<PlayerItemsPage, Error> {
+        self.cloud_save.get_player_items_page(player_id, None, after, access_class).await
+    }
 
-        let response = self.client.get(&get_url)
-            .header("Authorization", &self.auth_header)
-            .query(&params)
-            .send()
-            .await?;
-    
-            if response.status().is_success() {
-                let json: Value = response.json().await?;
-                Ok(json)
-            } else {
-                let text = response.text().await?;
-                Err(anyhow!("Failed to get player items, request_url: {}, error: {}", get_url, text).into())
+    pub async fn get_all_player_items(&self, player_id: &str, access_class: AccessClass) -> Result<Vec<PlayerItem>, Error> {
+        let mut items: Vec<PlayerItem> = vec![];
+        for _ in 0..PLAYER_ITEMS_PAGE_LIMIT {
+            let after = items.last().map(|item| item.key.clone());
+            let page = self.get_player_items_page(player_id, after.as_deref(), access_class).await?;
+            let is_last_page = page.links.next.is_none() || page.results.is_empty();
+            items.extend(page.results);
+            if is_last_page {
+                return Ok(items);
             }
+        }
+        Err(anyhow!("Player {} has more than {} pages of items", player_id, PLAYER_ITEMS_PAGE_LIMIT).into())
     }
 
-    pub async fn set_player_item(&self, player_id: &str, key: String, value: Value) -> Result<(), Error> {
-        let save_url = format!("{}/{}/items", self.players_url, player_id);
+    pub async fn get_player_item(&self, player_id: &str, key: &str, access_class: AccessClass) -> Result<Option<PlayerItem>, Error> {
+        let page = self.cloud_save.get_player_items_page(player_id, Some(key), None, access_class).await?;
+        Ok(page.results.into_iter().find(|item| item.key == key))
+    }
 
-        let request_body = SaveValueRequest {
-            key,
-            value,
+    pub async fn set_player_item(&self, player_id: &str, key: String, value: Value, access_class: AccessClass) -> Result<(), Error> {
+        self.cloud_save.set_player_item(player_id, &key, value, access_class).await?;
+        println!("Player item saved successfully.");
+        Ok(())
+    }
+
+    pub async fn get_save_data(&self, player_id: &str) -> Result<Value, Error> {
+        self.find_save_data(player_id).await?
+            .ok_or_else(|| anyhow!("No save data found for playerId: {}", player_id).into())
+    }
+
+    // Returns None for a player who has no save yet.
+    pub async fn find_save_data(&self, player_id: &str) -> Result<Option<Value>, Error> {
+        let Some(item) = self.get_player_item(player_id, &self.save_data_key, AccessClass::Default).await? else {
+            return Ok(None);
         };
 
-        let response = self.client.post(&save_url)
-            .header("Authorization", &self.auth_header)
-            .json(&request_body)
-            .send()
-            .await?;
+        // The game stores the save as a JSON string; fall back to the value itself if it isn't one.
+        match &item.value {
+            Value::String(value_str) => Ok(Some(serde_json::from_str(value_str).unwrap_or(item.value))),
+            _ => Ok(Some(item.value)),
+        }
+    }
 
-        if response.status().is_success() {
-            println!("Player item saved successfully.");
-        } else {
-            let text = response.text().await?;
-            return Err(anyhow!("Failed to save player item: {}", text).into());
+    pub fn validate_save_data(&self, save_data: &Value) -> Result<(), Error> {
+        let Some(validator) = &self.save_data_validator else {
+            return Ok(());
+        };
+
+        let violations: Vec<String> = validator.iter_errors(save_data)
+            .map(|e| format!("- {}: {}", if e.instance_path().as_str().is_empty() { "/" } else { e.instance_path().as_str() }, e))
+            .collect();
+        if violations.is_empty() {
+            return Ok(());
         }
 
-        Ok(())
+        let mut message = format!("Save data failed schema validation with {} violations:\n", violations.len());
+        message.push_str(&violations.iter().take(SCHEMA_VIOLATION_LIMIT).cloned().collect::<Vec<String>>().join("\n"));
+        if violations.len() > SCHEMA_VIOLATION_LIMIT {
+            message.push_str(&format!("\n... and {} more", violations.len() - SCHEMA_VIOLATION_LIMIT));
+        }
+        Err(anyhow!(message).into())
     }
 
-pub async fn get_save_data(&self, player_id: &str) -> Result<Value, Error> {
-    let player_items = self.get_player_items(player_id, self.save_data_key.clone()).await?;
-    let results_array = player_items.get("results").and_then(|v| v.as_array()).ok_or_else(|| anyhow!("Results array not found"))?;
-    let first_result = results_array.get(0).ok_or_else(|| anyhow!("No first result"))?;
-
-    // First, try to parse the value as a string and deserialize
-    if let Some(value_str) = first_result.get("value").and_then(|v| v.as_str()) {
-        match serde_json::from_str(value_str) {
-            Ok(save_data) => Ok(save_data),
-            Err(_) => {
-                // If parsing as a string fails, try to clone the value directly
-                Ok(first_result.get("value").cloned().ok_or_else(|| anyhow!("Failed to clone value directly"))?)
-            }
+    // Validates the save unless the write skips it, then snapshots `previous_save_data` before overwriting it
+    // and returns the snapshot id. `previous_save_data` is the save the caller built `save_data` from, or null
+    // for a player who had none, so the snapshot holds exactly what this write replaces.
+    pub async fn set_save_data(&self, player_id: &str, previous_save_data: &Value, save_data: Value, write: &SaveDataWrite) -> Result<i64, Error> {
+        let snapshot_id = self.snapshot_save_data(player_id, previous_save_data, &save_data, write).await?;
+        self.set_player_item(player_id, self.save_data_key.clone(), save_data, AccessClass::Default).await?;
+        Ok(snapshot_id)
+    }
+
+    // Like `set_save_data`, but records the grant before the save is written, so every granted save has an
+    // audit row. The row is removed again if the write fails. Returns the snapshot and grant ids.
+    pub async fn set_granted_save_data(&self, player_id: &str, previous_save_data: &Value, save_data: Value, rewards: &GiftCodeReward, reason: &str, write: &SaveDataWrite) -> Result<(i64, i64), Error> {
+        let snapshot_id = self.snapshot_save_data(player_id, previous_save_data, &save_data, write).await?;
+        let grant_id = self.db.insert_grant_in_db(player_id, &self.environment, rewards, reason, snapshot_id, write.actor_id).await?;
+        if let Err(e) = self.set_player_item(player_id, self.save_data_key.clone(), save_data, AccessClass::Default).await {
+            self.db.delete_grant_in_db(grant_id).await?;
+            return Err(e);
         }
-    } else {
-        // If the value is not a string, try to clone it directly
-        Ok(first_result.get("value").cloned().ok_or_else(|| anyhow!("Value field not found or not a string"))?)
+        Ok((snapshot_id, grant_id))
     }
-}
 
-    pub async fn set_save_data(&self, player_id: &str, save_data: Value) -> Result<(), Error> {
-        self.set_player_item(player_id, self.save_data_key.clone(), save_data).await?;
-        Ok(())
+    async fn snapshot_save_data(&self, player_id: &str, previous_save_data: &Value, save_data: &Value, write: &SaveDataWrite) -> Result<i64, Error> {
+        if write.skip_validation {
+            println!("Save data schema validation skipped by {} for playerId: {}", write.actor_id, player_id);
+        } else {
+            self.validate_save_data(save_data)?;
+        }
+
+        self.db.insert_save_snapshot_in_db(player_id, &self.environment, previous_save_data, write).await
     }
     
-    pub async fn update_subscription_data(&self, player_id: &str, product_id: &str, duration: i32, increase_save_count_by: u64) -> Result<(), Error> {
+    // Returns the current save of `to_player_id` (null if it has none) and the save it would get: a copy of
+    // `from_player_id`'s save with the save count moved past the current one.
+    pub async fn get_copied_save_data(&self, to_player_id: &str, from_player_id: &str, increase_save_count_by: u64) -> Result<(Value, Value), Error> {
         if increase_save_count_by < 1 {
-            return Err(anyhow!("increase_save_count_by must be greater than 0").into());
+            return Err(anyhow!("Increase save count by must be greater than 0").into());
         }
 
-        let mut save_data = self.get_save_data(player_id).await?;
-        let expires_at = Utc::now() + Duration::days(duration as i64);
+        let old_save_data = self.find_save_data(to_player_id).await?.unwrap_or(Value::Null);
+        let old_save_count = get_current_save_count(&old_save_data)
+            .map_err(|e| anyhow!("{} in {}", e, to_player_id))?;
 
-        let player_account_data = save_data
-            .get_mut("playerAccountData")
-            .and_then(|v| v.as_object_mut())
-            .ok_or_else(|| anyhow!("'playerAccountData' not found or null"))?;
+        let mut new_save_data = self.get_save_data(from_player_id).await?;
+        set_save_count(&mut new_save_data, old_save_count + increase_save_count_by)
+            .map_err(|e| anyhow!("{} in {}", e, from_player_id))?;
 
-        let shop_data = player_account_data
-            .get_mut("shopData")
-            .and_then(|v| v.as_object_mut())
-            .ok_or_else(|| anyhow!("'shopData' not found or null"))?;
+        Ok((old_save_data, new_save_data))
+    }
 
-        let shop_subscription_data = shop_data
-            .get_mut("shopSubscriptionData")
-            .and_then(|v| v.as_object_mut())
-            .ok_or_else(|| anyhow!("'shopSubscriptionData' not found or null"))?;
+    pub async fn update_subscription_data(&self, player_id: &str, product_id: &str, duration: i32, increase_save_count_by: u64, write: &SaveDataWrite) -> Result<i64, Error> {
+        let previous_save_data = self.get_save_data(player_id).await?;
+        let mut save_data = previous_save_data.clone();
+        update_subscription(&mut save_data, product_id, duration, increase_save_count_by)?;
+        self.set_save_data(player_id, &previous_save_data, save_data, write).await
+    }
 
-        let product = shop_subscription_data
-            .entry(product_id.to_string())
-            .or_insert_with(|| Value::Object(serde_json::Map::new()))
-            .as_object_mut().unwrap();
+    pub async fn get_player_files_page(&self, player_id: &str, after: Option<&str>) -> Result<PlayerFilesPage, Error> {
+        self.cloud_save.get_player_files_page(player_id, after).await
+    }
 
-        product.insert("expiresAt".to_string(), Value::String(expires_at.to_rfc3339()));
+    pub async fn get_all_player_files(&self, player_id: &str) -> Result<Vec<PlayerFile>, Error> {
+        let mut files: Vec<PlayerFile> = vec![];
+        for _ in 0..PLAYER_FILES_PAGE_LIMIT {
+            let after = files.last().map(|file| file.key.clone());
+            let page = self.get_player_files_page(player_id, after.as_deref()).await?;
+            let is_last_page = page.links.next.is_none() || page.results.is_empty();
+            files.extend(page.results);
+            if is_last_page {
+                return Ok(files);
+            }
+        }
+        Err(anyhow!("Player {} has more than {} pages of files", player_id, PLAYER_FILES_PAGE_LIMIT).into())
+    }
 
-        let player_progress_data = save_data
-            .get_mut("playerProgressData")
-            .and_then(|v| v.as_object_mut())
-            .ok_or_else(|| anyhow!("'playerProgressData' not found or null"))?;
-    
-        let save_count = player_progress_data
-            .get_mut("saveCount")
-            .and_then(|v| v.as_u64())
-            .ok_or_else(|| anyhow!("Unable to get or cast 'saveCount' as mutable number"))?;
-    
-        player_progress_data.insert("saveCount".to_string(), Value::Number((save_count + increase_save_count_by).into()));
+    pub async fn download_player_file(&self, player_id: &str, key: &str) -> Result<Vec<u8>, Error> {
+        self.cloud_save.download_player_file(player_id, key).await
+    }
 
-        self.set_save_data(player_id, save_data).await?;
+    pub async fn upload_player_file(&self, player_id: &str, key: &str, content: Vec<u8>, content_type: &str) -> Result<(), Error> {
+        self.cloud_save.upload_player_file(player_id, key, content, content_type).await?;
+        println!("Player file {} uploaded successfully for playerId: {}", key, player_id);
+        Ok(())
+    }
 
+    pub async fn delete_player_file(&self, player_id: &str, key: &str) -> Result<(), Error> {
+        self.cloud_save.delete_player_file(player_id, key).await?;
+        println!("Player file {} deleted successfully for playerId: {}", key, player_id);
         Ok(())
     }
 }
-
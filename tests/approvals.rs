@@ -0,0 +1,82 @@
+use std::sync::Arc;
+use serde_json::json;
+use unity_discordbot::approvals::ApprovableCommand;
+use unity_discordbot::db::Db;
+use unity_discordbot::memory_cloud_save::MemoryCloudSave;
+use unity_discordbot::models::{AccessClass, SaveDataWrite};
+use unity_discordbot::unity_service::UnityService;
+
+#[test]
+fn stores_commands_as_tagged_json() {
+    let command = ApprovableCommand::CopySaveData {
+        to_player_id: "player-1".to_string(),
+        from_player_id: "player-2".to_string(),
+        increase_save_count_by: 1,
+        skip_validation: false,
+        environment: "staging".to_string(),
+    };
+
+    let params = serde_json::to_value(&command).unwrap();
+    assert_eq!(params, json!({
+        "command": "copysavedata",
+        "toPlayerId": "player-1",
+        "fromPlayerId": "player-2",
+        "increaseSaveCountBy": 1,
+        "skipValidation": false,
+        "environment": "staging",
+    }));
+
+    let command: ApprovableCommand = serde_json::from_value(params).unwrap();
+    assert_eq!(command.name(), "copysavedata");
+    assert_eq!(command.environment(), "staging");
+    assert!(ApprovableCommand::NAMES.contains(&command.name()));
+}
+
+#[test]
+fn defaults_stored_commands_to_production() {
+    let command: ApprovableCommand = serde_json::from_value(json!({
+        "command": "updategameversion",
+        "versionNumber": "1.2.0",
+        "platform": "iOS",
+        "forceUpdate": false,
+    })).unwrap();
+    assert_eq!(command.environment(), "production");
+}
+
+#[test]
+fn rejects_unknown_commands() {
+    assert!(serde_json::from_value::<ApprovableCommand>(json!({"command": "removegiftcode", "code": "ABCDEFGHIJKLMNPQ"})).is_err());
+}
+
+#[tokio::test]
+async fn approved_restore_is_attributed_to_the_approver() {
+    std::env::set_var("UNITY_SAVE_DATA_KEY", "saveData");
+    std::env::set_var("SAVE_DATA_SCHEMA_PATH", "");
+    let db = Db::connect("sqlite::memory:").await.unwrap();
+    db.migrate(false).await.unwrap();
+    let db = Arc::new(db);
+    let unity_service = UnityService::with_cloud_save("production", Box::new(MemoryCloudSave::new()), db.clone()).unwrap();
+
+    let old_save_data = json!({ "playerProgressData": { "saveCount": 1, "level": 2 } });
+    let snapshot_id = db.insert_save_snapshot_in_db("player1", "production", &old_save_data, &SaveDataWrite::new("test", 1, false)).await.unwrap();
+    // The player kept playing while the request was waiting for approval.
+    let current_save_data = json!({ "playerProgressData": { "saveCount": 7, "level": 3 } });
+    unity_service.set_player_item("player1", "saveData".to_string(), json!(current_save_data.to_string()), AccessClass::Default).await.unwrap();
+
+    let command = ApprovableCommand::RestoreSaveData {
+        player_id: "player1".to_string(),
+        snapshot_id,
+        increase_save_count_by: 1,
+        skip_validation: false,
+        environment: "production".to_string(),
+    };
+    let approved_by = 223456789012345678;
+    let command: ApprovableCommand = serde_json::from_value(serde_json::to_value(&command).unwrap()).unwrap();
+    command.execute(&unity_service, &db, approved_by).await.unwrap();
+
+    assert_eq!(unity_service.get_save_data("player1").await.unwrap(), json!({ "playerProgressData": { "saveCount": 8, "level": 2 } }));
+    let snapshots = db.get_save_snapshots_in_db("player1", "production", 10).await.unwrap();
+    assert_eq!(snapshots[0].command, "restoresavedata");
+    assert_eq!(snapshots[0].actor_id, approved_by);
+    assert_eq!(serde_json::from_str::<serde_json::Value>(&snapshots[0].save_data).unwrap(), current_save_data);
+}
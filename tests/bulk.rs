@@ -0,0 +1,38 @@
+use unity_discordbot::bulk::{get_bulk_report, parse_bulk_csv, BulkResult};
+
+#[test]
+fn parses_rows_with_optional_columns() {
+    let csv = "player_id,days,rewards\n\
+        player-1,,\n\
+        player-2, 30 ,\"{\"\"currencyRewards\"\": [], \"\"itemRewards\"\": [], \"\"xpReward\"\": 5}\"\n";
+
+    let rows = parse_bulk_csv(csv.as_bytes()).unwrap();
+    assert_eq!(rows.len(), 2);
+    assert_eq!(rows[0].player_id, "player-1");
+    assert_eq!(rows[0].days, None);
+    assert_eq!(rows[1].days, Some(30));
+    assert_eq!(rows[1].product_id, None);
+    assert!(rows[1].rewards.as_deref().unwrap().contains("xpReward"));
+}
+
+#[test]
+fn rejects_invalid_csv() {
+    assert!(parse_bulk_csv(b"player_id\n").is_err());
+    assert!(parse_bulk_csv(b"days\n30\n").is_err());
+    assert!(parse_bulk_csv(b"player_id,days\nplayer-1,soon\n").is_err());
+    assert!(parse_bulk_csv(b"player_id\n\"\"\n").is_err());
+    assert!(parse_bulk_csv(b"player_id,days\nplayer-1,30\nplayer-2,30\nplayer-1,7\n").unwrap_err().to_string().starts_with("Invalid CSV row 3"));
+}
+
+#[test]
+fn writes_report() {
+    let results = vec![
+        BulkResult { player_id: "player-1".to_string(), success: true, message: "grant 1, snapshot 2".to_string() },
+        BulkResult { player_id: "player-2".to_string(), success: false, message: "'playerProgressData' not found or null".to_string() },
+    ];
+
+    let report = get_bulk_report(&results).unwrap();
+    assert_eq!(report, "player_id,success,message\n\
+        player-1,true,\"grant 1, snapshot 2\"\n\
+        player-2,false,'playerProgressData' not found or null\n");
+}
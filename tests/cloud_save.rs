@@ -0,0 +1,169 @@
+use std::sync::Arc;
+use serde_json::json;
+use unity_discordbot::cloud_save::CloudSave;
+use unity_discordbot::config::CloudSaveConfig;
+use unity_discordbot::db::Db;
+use unity_discordbot::directory_cloud_save::DirectoryCloudSave;
+use unity_discordbot::memory_cloud_save::MemoryCloudSave;
+use unity_discordbot::models::{AccessClass, GamePlatform, GameVersion, GiftCode, GiftCodeReward, SaveDataWrite};
+use unity_discordbot::unity_error::{UnityError, UnityErrorKind};
+use unity_discordbot::unity_service::UnityService;
+
+fn temp_dir(name: &str) -> String {
+    let dir = std::env::temp_dir().join(format!("unity_discordbot_{}_{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    dir.to_string_lossy().to_string()
+}
+
+fn is_not_found(error: &unity_discordbot::Error) -> bool {
+    error.downcast_ref::<UnityError>().is_some_and(|e| e.kind == UnityErrorKind::NotFound)
+}
+
+async fn assert_custom_items(cloud_save: &dyn CloudSave) {
+    cloud_save.set_custom_item("gift_codes", "b", json!("second")).await.unwrap();
+    cloud_save.set_custom_item("gift_codes", "a", json!({ "amount": 1 })).await.unwrap();
+    cloud_save.set_custom_item("gift_codes", "a", json!({ "amount": 2 })).await.unwrap();
+
+    let items = cloud_save.get_custom_items("gift_codes", None).await.unwrap();
+    assert_eq!(items.iter().map(|item| item.key.as_str()).collect::<Vec<_>>(), vec!["a", "b"]);
+    assert_eq!(items[0].value, json!({ "amount": 2 }));
+
+    let items = cloud_save.get_custom_items("gift_codes", Some("b,c")).await.unwrap();
+    assert_eq!(items.len(), 1);
+    assert_eq!(items[0].value, json!("second"));
+    assert!(cloud_save.get_custom_items("game_version", None).await.unwrap().is_empty());
+
+    cloud_save.delete_custom_item("gift_codes", "a").await.unwrap();
+    assert!(is_not_found(&cloud_save.delete_custom_item("gift_codes", "a").await.unwrap_err()));
+    assert!(cloud_save.set_custom_item("gift_codes", "../a", json!(1)).await.is_err());
+}
+
+async fn assert_player_items(cloud_save: &dyn CloudSave) {
+    for key in ["c", "a", "b"] {
+        cloud_save.set_player_item("player1", key, json!(key), AccessClass::Default).await.unwrap();
+    }
+    cloud_save.set_player_item("player1", "secret", json!(1), AccessClass::Private).await.unwrap();
+
+    let page = cloud_save.get_player_items_page("player1", None, None, AccessClass::Default).await.unwrap();
+    assert_eq!(page.results.iter().map(|item| item.key.as_str()).collect::<Vec<_>>(), vec!["a", "b", "c"]);
+    assert!(page.links.next.is_none());
+
+    let page = cloud_save.get_player_items_page("player1", None, Some("a"), AccessClass::Default).await.unwrap();
+    assert_eq!(page.results.iter().map(|item| item.key.as_str()).collect::<Vec<_>>(), vec!["b", "c"]);
+
+    let page = cloud_save.get_player_items_page("player1", Some("secret"), None, AccessClass::Private).await.unwrap();
+    assert_eq!(page.results.len(), 1);
+    assert!(cloud_save.get_player_items_page("player2", None, None, AccessClass::Default).await.unwrap().results.is_empty());
+}
+
+async fn assert_player_files(cloud_save: &dyn CloudSave) {
+    cloud_save.upload_player_file("player1", "replay", b"content".to_vec(), "application/octet-stream").await.unwrap();
+
+    let page = cloud_save.get_player_files_page("player1", None).await.unwrap();
+    assert_eq!(page.results.len(), 1);
+    assert_eq!(page.results[0].key, "replay");
+    assert_eq!(page.results[0].size, 7);
+    assert_eq!(cloud_save.download_player_file("player1", "replay").await.unwrap(), b"content".to_vec());
+
+    cloud_save.delete_player_file("player1", "replay").await.unwrap();
+    assert!(is_not_found(&cloud_save.download_player_file("player1", "replay").await.unwrap_err()));
+    assert!(is_not_found(&cloud_save.delete_player_file("player1", "replay").await.unwrap_err()));
+}
+
+#[tokio::test]
+async fn memory_cloud_save() {
+    let cloud_save = MemoryCloudSave::new();
+    assert_custom_items(&cloud_save).await;
+    assert_player_items(&cloud_save).await;
+    assert_player_files(&cloud_save).await;
+
+    let items = cloud_save.get_custom_items("gift_codes", None).await.unwrap();
+    assert!(items[0].write_lock.is_some());
+    assert!(items[0].created.is_some());
+}
+
+#[tokio::test]
+async fn directory_cloud_save() {
+    let root = temp_dir("directory_cloud_save");
+    let cloud_save = DirectoryCloudSave::new(&root).unwrap();
+    assert_custom_items(&cloud_save).await;
+    assert_player_items(&cloud_save).await;
+    assert_player_files(&cloud_save).await;
+
+    // Saves can be edited by hand.
+    std::fs::write(format!("{}/players/player1/items/a.json", root), r#"{ "edited": true }"#).unwrap();
+    let page = cloud_save.get_player_items_page("player1", Some("a"), None, AccessClass::Default).await.unwrap();
+    assert_eq!(page.results[0].value, json!({ "edited": true }));
+    assert!(std::path::Path::new(&format!("{}/players/player1/private/items/secret.json", root)).exists());
+    assert!(cloud_save.get_player_items_page("../player1", None, None, AccessClass::Default).await.is_err());
+
+    std::fs::remove_dir_all(&root).unwrap();
+}
+
+#[test]
+fn parses_cloud_save_config() {
+    let config: CloudSaveConfig = serde_json::from_value(json!({ "backend": "directory", "path": "data" })).unwrap();
+    assert!(matches!(config, CloudSaveConfig::Directory { path } if path == "data"));
+    assert!(matches!(serde_json::from_value(json!({ "backend": "memory" })).unwrap(), CloudSaveConfig::Memory));
+    assert!(matches!(CloudSaveConfig::default(), CloudSaveConfig::Http));
+}
+
+#[tokio::test]
+async fn unity_service_on_memory_cloud_save() {
+    std::env::set_var("UNITY_SAVE_DATA_KEY", "saveData");
+    std::env::set_var("SAVE_DATA_SCHEMA_PATH", "");
+    let db = Db::connect("sqlite::memory:").await.unwrap();
+    db.migrate(false).await.unwrap();
+    let db = Arc::new(db);
+    let unity_service = UnityService::with_cloud_save("production", Box::new(MemoryCloudSave::new()), db.clone()).unwrap();
+
+    let gift_code: GiftCode = serde_json::from_value(json!({
+        "title": "Launch",
+        "subtitle": "Thanks for playing",
+        "amount": 10,
+        "duration": 7,
+        "expiredAt": "2030-01-01T00:00:00Z",
+        "rewards": { "currencyRewards": [], "itemRewards": [], "xpReward": 100 },
+        "channelId": 1,
+        "messageId": "2",
+        "buttonId": "launch",
+    })).unwrap();
+    unity_service.save_gift_code("launch", &gift_code).await.unwrap();
+    assert_eq!(unity_service.get_gift_code("launch".to_string()).await.unwrap().amount, 10);
+    assert_eq!(unity_service.get_gift_code_count().await.unwrap(), 1);
+    unity_service.delete_gift_code("launch").await.unwrap();
+    let error = unity_service.get_gift_code("launch".to_string()).await.unwrap_err();
+    assert!(is_not_found(&error));
+
+    assert!(unity_service.get_game_version(&GamePlatform::iOS).await.unwrap().is_none());
+    let game_version = GameVersion { version_number: "1.2.0".to_string(), force_update: true };
+    unity_service.update_game_version(&game_version, GamePlatform::iOS).await.unwrap();
+    assert_eq!(unity_service.get_game_version(&GamePlatform::iOS).await.unwrap().unwrap().version_number, "1.2.0");
+    assert!(unity_service.get_game_version(&GamePlatform::Android).await.unwrap().is_none());
+
+    // The game writes its save as a JSON string.
+    let save_data = json!({ "playerProgressData": { "saveCount": 3 } });
+    unity_service.set_player_item("player1", "saveData".to_string(), json!(save_data.to_string()), AccessClass::Default).await.unwrap();
+    assert_eq!(unity_service.get_save_data("player1").await.unwrap(), save_data);
+
+    let new_save_data = json!({ "playerProgressData": { "saveCount": 4 } });
+    let write = SaveDataWrite::new("test", 1, false);
+    let snapshot_id = unity_service.set_save_data("player1", &save_data, new_save_data.clone(), &write).await.unwrap();
+    assert_eq!(unity_service.get_save_data("player1").await.unwrap(), new_save_data);
+    let snapshot = db.get_save_snapshot_in_db("player1", "production", snapshot_id).await.unwrap().unwrap();
+    assert_eq!(serde_json::from_str::<serde_json::Value>(&snapshot.save_data).unwrap(), save_data);
+
+    let rewards: GiftCodeReward = serde_json::from_value(json!({ "currencyRewards": [], "itemRewards": [], "xpReward": 50 })).unwrap();
+    let granted_save_data = json!({ "playerProgressData": { "saveCount": 5, "xp": 50 } });
+    let (snapshot_id, grant_id) = unity_service.set_granted_save_data("player1", &new_save_data, granted_save_data.clone(), &rewards, "compensation", &write).await.unwrap();
+    assert_eq!(unity_service.get_save_data("player1").await.unwrap(), granted_save_data);
+    let grants = db.get_grants_in_db("player1", "production", 10).await.unwrap();
+    assert_eq!((grants[0].id, grants[0].snapshot_id), (grant_id, snapshot_id));
+
+    // A player's first save is snapshotted as null.
+    assert!(unity_service.find_save_data("player2").await.unwrap().is_none());
+    let snapshot_id = unity_service.set_save_data("player2", &serde_json::Value::Null, new_save_data.clone(), &write).await.unwrap();
+    assert_eq!(unity_service.get_save_data("player2").await.unwrap(), new_save_data);
+    let snapshot = db.get_save_snapshot_in_db("player2", "production", snapshot_id).await.unwrap().unwrap();
+    assert_eq!(snapshot.save_data, "null");
+}
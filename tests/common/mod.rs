@@ -0,0 +1,94 @@
+#![allow(dead_code)]
+
+use std::sync::{Arc, Mutex};
+use sqlx::any::{install_default_drivers, AnyPoolOptions};
+use sqlx::Executor;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+
+#[derive(Clone, Debug)]
+pub struct StubRequest {
+    pub method: String,
+    pub path: String,
+    pub authorization: String,
+}
+
+pub type StubLog = Arc<Mutex<Vec<StubRequest>>>;
+
+// Serves one response per connection, enough for reqwest talking to a fake Unity API. The handler
+// also gets the requests served so far, so it can fail the first few.
+pub async fn start_stub<F>(handler: F) -> (String, StubLog)
+where
+    F: Fn(&StubRequest, &[StubRequest]) -> (u16, String) + Send + Sync + 'static,
+{
+    // Rate limited responses ask for an immediate retry so tests don't wait.
+    start_stub_with_retry_after(handler, 0).await
+}
+
+// Same as `start_stub`, with the Retry-After seconds sent on rate limited responses.
+pub async fn start_stub_with_retry_after<F>(handler: F, retry_after_secs: u64) -> (String, StubLog)
+where
+    F: Fn(&StubRequest, &[StubRequest]) -> (u16, String) + Send + Sync + 'static,
+{
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let base_url = format!("http://{}", listener.local_addr().unwrap());
+    let log: StubLog = Arc::new(Mutex::new(vec![]));
+    let handler = Arc::new(handler);
+
+    let server_log = log.clone();
+    tokio::spawn(async move {
+        loop {
+            let Ok((mut stream, _)) = listener.accept().await else {
+                return;
+            };
+            let mut buffer = vec![0; 8192];
+            let mut read = 0;
+            while !String::from_utf8_lossy(&buffer[..read]).contains("\r\n\r\n") {
+                read += stream.read(&mut buffer[read..]).await.unwrap();
+            }
+
+            let head = String::from_utf8_lossy(&buffer[..read]).to_string();
+            let mut lines = head.lines();
+            let mut request_line = lines.next().unwrap().split(' ');
+            let request = StubRequest {
+                method: request_line.next().unwrap().to_string(),
+                path: request_line.next().unwrap().to_string(),
+                authorization: lines
+                    .find_map(|line| line.strip_prefix("authorization: "))
+                    .unwrap_or_default()
+                    .to_string(),
+            };
+
+            let (status, body) = {
+                let mut log = server_log.lock().unwrap();
+                let response = handler(&request, &log);
+                log.push(request);
+                response
+            };
+            let retry_after = if status == 429 { format!("Retry-After: {}\r\n", retry_after_secs) } else { String::new() };
+            let response = format!(
+                "HTTP/1.1 {} Stub\r\nContent-Type: application/json\r\nContent-Length: {}\r\n{}Connection: close\r\n\r\n{}",
+                status, body.len(), retry_after, body
+            );
+            stream.write_all(response.as_bytes()).await.unwrap();
+        }
+    });
+
+    (base_url, log)
+}
+
+// Set to e.g. postgres://postgres@localhost/bot_test and run `cargo test -- --ignored` to also test against Postgres.
+const TEST_POSTGRES_URL: &str = "TEST_POSTGRES_URL";
+
+// Test binaries run in parallel, so each Postgres test gets its own schema, recreated empty. The returned URL
+// puts every connection's search_path on it.
+pub async fn postgres_test_url(schema: &str) -> String {
+    let url = std::env::var(TEST_POSTGRES_URL).unwrap_or_else(|_| panic!("{} must be set to run the Postgres tests", TEST_POSTGRES_URL));
+    install_default_drivers();
+    let pool = AnyPoolOptions::new().max_connections(1).connect(&url).await.unwrap();
+    pool.execute(format!("DROP SCHEMA IF EXISTS {0} CASCADE; CREATE SCHEMA {0};", schema).as_str()).await.unwrap();
+    pool.close().await;
+
+    let separator = if url.contains('?') { '&' } else { '?' };
+    format!("{}{}options[search_path]={}", url, separator, schema)
+}
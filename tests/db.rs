@@ -0,0 +1,196 @@
+mod common;
+
+use chrono::{Duration, Utc};
+use serde_json::json;
+use common::postgres_test_url;
+use unity_discordbot::db::Db;
+use unity_discordbot::models::{ApprovalStatus, GiftCodeRedemption, GiftCodeReward, SaveDataWrite};
+
+async fn migrated_db(url: &str) -> Db {
+    let db = Db::connect(url).await.unwrap();
+    db.migrate(false).await.unwrap();
+    db
+}
+
+fn redemption(gift_code_key: &str, user_id: u64) -> GiftCodeRedemption {
+    GiftCodeRedemption {
+        gift_code_key: gift_code_key.to_string(),
+        user_id,
+        username: "player".to_string(),
+        guild_id: None,
+        channel_id: 1234567890123456789,
+        remaining_amount: 9,
+        redeemed_at: Utc::now().to_rfc3339(),
+    }
+}
+
+async fn assert_redemptions(db: &Db) {
+    let key = "ABCDEFGHIJKLMNPQ".to_string();
+    let user_id = 987654321098765432;
+
+    assert!(!db.is_user_redeemed_gift_code_in_db(&key, user_id).await.unwrap());
+    db.redeem_gift_code_in_db(&redemption(&key, user_id)).await.unwrap();
+    assert!(db.is_user_redeemed_gift_code_in_db(&key, user_id).await.unwrap());
+    assert!(db.redeem_gift_code_in_db(&redemption(&key, user_id)).await.is_err());
+
+    assert!(db.revoke_gift_code_redemption_in_db(&key, user_id, true).await.unwrap());
+    assert!(!db.revoke_gift_code_redemption_in_db(&key, user_id, true).await.unwrap());
+    assert!(!db.is_user_redeemed_gift_code_in_db(&key, user_id).await.unwrap());
+}
+
+async fn assert_blocklist(db: &Db) {
+    let user_id = 987654321098765432;
+    let owner_id = 123456789012345678;
+
+    assert!(!db.is_user_blocked_in_db(user_id).await.unwrap());
+    db.block_user_in_db(user_id, None, None, owner_id).await.unwrap();
+    assert!(db.is_user_blocked_in_db(user_id).await.unwrap());
+
+    let expired = (Utc::now() - Duration::days(1)).to_rfc3339();
+    db.block_user_in_db(user_id, Some("resold codes"), Some(&expired), owner_id).await.unwrap();
+    assert!(!db.is_user_blocked_in_db(user_id).await.unwrap());
+
+    assert!(db.unblock_user_in_db(user_id).await.unwrap());
+    assert!(!db.unblock_user_in_db(user_id).await.unwrap());
+}
+
+async fn assert_gift_code_tracking(db: &Db) {
+    let key = "ABCDEFGHIJKLMNPQ".to_string();
+
+    assert_eq!(db.get_gift_code_initial_amount_in_db(&key).await.unwrap(), None);
+    db.record_gift_code_in_db(&key, 10).await.unwrap();
+    db.redeem_gift_code_in_db(&redemption(&key, 1)).await.unwrap();
+    db.redeem_gift_code_in_db(&redemption(&key, 2)).await.unwrap();
+    assert_eq!(db.count_gift_code_redemptions_in_db(&key).await.unwrap(), 2);
+
+    db.revoke_gift_code_redemption_in_db(&key, 1, false).await.unwrap();
+    db.revoke_gift_code_redemption_in_db(&key, 2, true).await.unwrap();
+    assert_eq!(db.count_gift_code_redemptions_in_db(&key).await.unwrap(), 0);
+    assert_eq!(db.count_gift_code_revocations_in_db(&key).await.unwrap(), 1);
+
+    db.record_gift_code_in_db(&key, 12).await.unwrap();
+    assert_eq!(db.get_gift_code_initial_amount_in_db(&key).await.unwrap(), Some(12));
+    assert_eq!(db.count_gift_code_revocations_in_db(&key).await.unwrap(), 1);
+}
+
+async fn assert_save_snapshots(db: &Db) {
+    let player_id = "player-1";
+    let write = SaveDataWrite::new("copysavedata", 123456789012345678, false);
+
+    let first = db.insert_save_snapshot_in_db(player_id, "production", &json!({"playerProgressData": {"saveCount": 1}}), &write).await.unwrap();
+    let second = db.insert_save_snapshot_in_db(player_id, "production", &json!({"playerProgressData": {"saveCount": 2}}), &write).await.unwrap();
+    assert!(second > first);
+
+    let staging = db.insert_save_snapshot_in_db(player_id, "staging", &json!({"playerProgressData": {"saveCount": 3}}), &write).await.unwrap();
+
+    let snapshots = db.get_save_snapshots_in_db(player_id, "production", 10).await.unwrap();
+    assert_eq!(snapshots.iter().map(|s| s.id).collect::<Vec<_>>(), vec![second, first]);
+    assert_eq!(snapshots[0].actor_id, 123456789012345678);
+    assert_eq!(snapshots[0].environment, "production");
+
+    let snapshot = db.get_save_snapshot_in_db(player_id, "production", first).await.unwrap().unwrap();
+    assert_eq!(snapshot.command, "copysavedata");
+    assert!(db.get_save_snapshot_in_db("player-2", "production", first).await.unwrap().is_none());
+
+    // A staging snapshot can't be restored to production.
+    assert!(db.get_save_snapshot_in_db(player_id, "production", staging).await.unwrap().is_none());
+    assert_eq!(db.get_save_snapshots_in_db(player_id, "staging", 10).await.unwrap().len(), 1);
+}
+
+async fn assert_grants(db: &Db) {
+    let player_id = "player-1";
+    let rewards: GiftCodeReward = serde_json::from_value(json!({"currencyRewards": [], "itemRewards": [], "xpReward": 50})).unwrap();
+
+    let first = db.insert_grant_in_db(player_id, "production", &rewards, "lost progress", 1, 123456789012345678).await.unwrap();
+    let second = db.insert_grant_in_db(player_id, "production", &rewards, "second bug", 2, 123456789012345678).await.unwrap();
+    let staging = db.insert_grant_in_db(player_id, "staging", &rewards, "testing", 3, 123456789012345678).await.unwrap();
+
+    let grants = db.get_grants_in_db(player_id, "production", 10).await.unwrap();
+    assert_eq!(grants.iter().map(|g| g.id).collect::<Vec<_>>(), vec![second, first]);
+    assert_eq!(grants[1].reason, "lost progress");
+    assert_eq!(grants[1].snapshot_id, 1);
+    assert_eq!(grants[1].environment, "production");
+    assert!(db.get_grants_in_db("player-2", "production", 10).await.unwrap().is_empty());
+
+    let grants = db.get_grants_in_db(player_id, "staging", 10).await.unwrap();
+    assert_eq!(grants.iter().map(|g| g.id).collect::<Vec<_>>(), vec![staging]);
+}
+
+async fn assert_approvals(db: &Db) {
+    let owner_id = 123456789012345678;
+    let other_owner_id = 223456789012345678;
+    let expires_at = (Utc::now() + Duration::hours(1)).to_rfc3339();
+    let params = json!({"command": "updategameversion", "versionNumber": "1.2.0", "platform": "iOS", "forceUpdate": true});
+
+    let approval_id = db.insert_approval_in_db("updategameversion", &params, owner_id, 1234567890123456789, &expires_at).await.unwrap();
+    let approval = db.get_approval_in_db(approval_id).await.unwrap().unwrap();
+    assert_eq!(approval.status, ApprovalStatus::Pending);
+    assert_eq!(approval.requested_by, owner_id);
+    assert_eq!(approval.decided_by, None);
+    assert_eq!(serde_json::from_str::<serde_json::Value>(&approval.params).unwrap(), params);
+
+    assert!(db.decide_approval_in_db(approval_id, ApprovalStatus::Approved, other_owner_id).await.unwrap());
+    assert!(!db.decide_approval_in_db(approval_id, ApprovalStatus::Rejected, owner_id).await.unwrap());
+    let approval = db.get_approval_in_db(approval_id).await.unwrap().unwrap();
+    assert_eq!(approval.status, ApprovalStatus::Approved);
+    assert_eq!(approval.decided_by, Some(other_owner_id));
+    assert_eq!(approval.outcome, None);
+
+    assert!(db.finish_approval_in_db(approval_id, ApprovalStatus::Failed, "Save data not found").await.unwrap());
+    assert!(!db.finish_approval_in_db(approval_id, ApprovalStatus::Executed, "Done").await.unwrap());
+    let approval = db.get_approval_in_db(approval_id).await.unwrap().unwrap();
+    assert_eq!(approval.status, ApprovalStatus::Failed);
+    assert_eq!(approval.outcome.as_deref(), Some("Save data not found"));
+
+    assert!(db.get_approval_in_db(approval_id + 1).await.unwrap().is_none());
+}
+
+#[tokio::test]
+async fn redemptions_on_sqlite() {
+    let db = migrated_db("sqlite::memory:").await;
+    assert_redemptions(&db).await;
+}
+
+#[tokio::test]
+async fn blocklist_on_sqlite() {
+    let db = migrated_db("sqlite::memory:").await;
+    assert_blocklist(&db).await;
+}
+
+#[tokio::test]
+async fn gift_code_tracking_on_sqlite() {
+    let db = migrated_db("sqlite::memory:").await;
+    assert_gift_code_tracking(&db).await;
+}
+
+#[tokio::test]
+async fn save_snapshots_on_sqlite() {
+    let db = migrated_db("sqlite::memory:").await;
+    assert_save_snapshots(&db).await;
+}
+
+#[tokio::test]
+async fn grants_on_sqlite() {
+    let db = migrated_db("sqlite::memory:").await;
+    assert_grants(&db).await;
+}
+
+#[tokio::test]
+async fn approvals_on_sqlite() {
+    let db = migrated_db("sqlite::memory:").await;
+    assert_approvals(&db).await;
+}
+
+#[tokio::test]
+#[ignore = "needs a Postgres database in TEST_POSTGRES_URL"]
+async fn queries_on_postgres() {
+    let url = postgres_test_url("db_test").await;
+
+    let db = migrated_db(&url).await;
+    assert_redemptions(&db).await;
+    assert_blocklist(&db).await;
+    assert_gift_code_tracking(&db).await;
+    assert_save_snapshots(&db).await;
+    assert_grants(&db).await;
+    assert_approvals(&db).await;
+}
@@ -0,0 +1,78 @@
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use unity_discordbot::config::{get_unity_environments, CloudSaveConfig, UnityEnvironmentConfig};
+use unity_discordbot::db::Db;
+use unity_discordbot::environments::UnityEnvironments;
+use unity_discordbot::memory_cloud_save::MemoryCloudSave;
+use unity_discordbot::unity_service::UnityService;
+
+async fn create_environments(names: &[&str]) -> Result<UnityEnvironments, unity_discordbot::Error> {
+    std::env::set_var("UNITY_SAVE_DATA_KEY", "saveData");
+    std::env::set_var("SAVE_DATA_SCHEMA_PATH", "");
+    let db = Arc::new(Db::connect("sqlite::memory:").await.unwrap());
+    let mut services = BTreeMap::new();
+    for name in names {
+        let unity_service = UnityService::with_cloud_save(name, Box::new(MemoryCloudSave::new()), db.clone()).unwrap();
+        services.insert(name.to_string(), Arc::new(unity_service));
+    }
+    UnityEnvironments::from_services(services)
+}
+
+fn http_environment(project_id: Option<&str>, environment_id: &str) -> UnityEnvironmentConfig {
+    UnityEnvironmentConfig {
+        unity_project_id: project_id.map(|id| id.to_string()),
+        unity_environment_id: Some(environment_id.to_string()),
+        unity_key_id: Some("key".to_string()),
+        unity_secret_key: Some("secret".to_string()),
+        cloud_save: None,
+    }
+}
+
+#[tokio::test]
+async fn single_environment_defaults_to_production() {
+    let environments = create_environments(&["production"]).await.unwrap();
+    assert_eq!(environments.select(None, false).unwrap().environment(), "production");
+    assert_eq!(environments.select(None, true).unwrap().environment(), "production");
+    assert!(environments.select(Some("staging"), false).is_err());
+}
+
+#[tokio::test]
+async fn writes_require_an_environment_once_several_are_configured() {
+    let environments = create_environments(&["production", "staging"]).await.unwrap();
+    assert_eq!(environments.names(), vec!["production", "staging"]);
+    assert_eq!(environments.select(None, false).unwrap().environment(), "production");
+    assert!(environments.select(None, true).is_err());
+    assert_eq!(environments.select(Some("production"), true).unwrap().environment(), "production");
+    assert_eq!(environments.select(Some("staging"), true).unwrap().environment(), "staging");
+
+    assert!(create_environments(&["staging"]).await.is_err());
+}
+
+#[test]
+fn top_level_settings_are_production_without_environments() {
+    let environments = get_unity_environments(BTreeMap::new(), http_environment(Some("project"), "live")).unwrap();
+    assert_eq!(environments.keys().collect::<Vec<_>>(), vec!["production"]);
+    assert_eq!(environments["production"].unity_environment_id.as_deref(), Some("live"));
+}
+
+#[test]
+fn environments_inherit_top_level_settings() {
+    let configured = BTreeMap::from([
+        ("production".to_string(), http_environment(None, "live")),
+        ("staging".to_string(), http_environment(None, "staging")),
+        ("development".to_string(), UnityEnvironmentConfig {
+            cloud_save: Some(CloudSaveConfig::Memory),
+            ..UnityEnvironmentConfig::default()
+        }),
+    ]);
+    let environments = get_unity_environments(configured, http_environment(Some("project"), "live")).unwrap();
+    assert_eq!(environments["staging"].unity_project_id.as_deref(), Some("project"));
+    assert_eq!(environments["staging"].unity_environment_id.as_deref(), Some("staging"));
+    assert!(matches!(environments["development"].cloud_save(), CloudSaveConfig::Memory));
+
+    let missing_production = BTreeMap::from([("staging".to_string(), http_environment(Some("project"), "staging"))]);
+    assert!(get_unity_environments(missing_production, UnityEnvironmentConfig::default()).is_err());
+
+    let missing_credentials = BTreeMap::from([("production".to_string(), http_environment(None, "live"))]);
+    assert!(get_unity_environments(missing_credentials, UnityEnvironmentConfig::default()).is_err());
+}
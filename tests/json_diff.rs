@@ -0,0 +1,68 @@
+use serde_json::json;
+use unity_discordbot::json_diff::{diff_json, get_diff_embed, DiffKind};
+
+#[test]
+fn reports_added_removed_and_changed_paths() {
+    let old = json!({
+        "playerProgressData": { "saveCount": 3, "level": 10 },
+        "inventory": [1, 2, 3],
+        "a/b": true,
+    });
+    let new = json!({
+        "playerProgressData": { "saveCount": 4, "xp": 50 },
+        "inventory": [1, 5],
+        "a/b": true,
+    });
+
+    let entries = diff_json(&old, &new);
+    let summary: Vec<(DiffKind, &str)> = entries.iter().map(|e| (e.kind, e.path.as_str())).collect();
+
+    assert_eq!(summary, vec![
+        (DiffKind::Changed, "/inventory/1"),
+        (DiffKind::Removed, "/inventory/2"),
+        (DiffKind::Removed, "/playerProgressData/level"),
+        (DiffKind::Changed, "/playerProgressData/saveCount"),
+        (DiffKind::Added, "/playerProgressData/xp"),
+    ]);
+    assert_eq!(entries[3].old_value, Some(json!(3)));
+    assert_eq!(entries[3].new_value, Some(json!(4)));
+}
+
+#[test]
+fn escapes_pointer_tokens_and_handles_type_changes() {
+    let entries = diff_json(&json!({ "a/b~c": 1 }), &json!({ "a/b~c": "1" }));
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0].path, "/a~1b~0c");
+    assert_eq!(entries[0].kind, DiffKind::Changed);
+
+    assert!(diff_json(&json!({ "x": [1, { "y": null }] }), &json!({ "x": [1, { "y": null }] })).is_empty());
+}
+
+#[test]
+fn truncates_long_paths_in_embed_field_names() {
+    let key = "k".repeat(300);
+    let entries = diff_json(&json!({}), &json!({ key: 1 }));
+    let embed = serde_json::to_value(get_diff_embed("Diff", &entries)).unwrap();
+
+    let name = embed["fields"][3]["name"].as_str().unwrap();
+    assert!(name.chars().count() <= 256);
+    assert!(name.starts_with("Added `/kkk"));
+}
+
+#[test]
+fn keeps_embed_within_total_length_limit() {
+    let old: serde_json::Map<String, serde_json::Value> = (0..15).map(|i| (format!("{}{}", i, "k".repeat(300)), json!("a".repeat(300)))).collect();
+    let new: serde_json::Map<String, serde_json::Value> = (0..15).map(|i| (format!("{}{}", i, "k".repeat(300)), json!("b".repeat(300)))).collect();
+    let entries = diff_json(&json!(old), &json!(new));
+    assert_eq!(entries.len(), 15);
+    let embed = serde_json::to_value(get_diff_embed("Diff", &entries)).unwrap();
+
+    let fields = embed["fields"].as_array().unwrap();
+    let footer = embed["footer"]["text"].as_str().unwrap();
+    let length = embed["title"].as_str().unwrap().chars().count()
+        + footer.chars().count()
+        + fields.iter().map(|field| field["name"].as_str().unwrap().chars().count() + field["value"].as_str().unwrap().chars().count()).sum::<usize>();
+    assert!(length <= 6000);
+    assert!(fields.len() < 3 + 15);
+    assert_eq!(footer, format!("…and {} more differences in the attached file", 15 - (fields.len() - 3)));
+}
@@ -0,0 +1,82 @@
+mod common;
+
+use sqlx::any::{install_default_drivers, AnyPoolOptions};
+use sqlx::{AnyPool, Executor};
+use common::postgres_test_url;
+use unity_discordbot::db::{DbBackend, PostgresBackend, SqliteBackend};
+use unity_discordbot::migrations::{self, MIGRATIONS};
+
+async fn memory_pool() -> AnyPool {
+    install_default_drivers();
+    AnyPoolOptions::new()
+        .max_connections(1)
+        .idle_timeout(None)
+        .max_lifetime(None)
+        .connect("sqlite::memory:")
+        .await
+        .unwrap()
+}
+
+async fn postgres_pool() -> AnyPool {
+    let url = postgres_test_url("migrations_test").await;
+    AnyPoolOptions::new().max_connections(1).connect(&url).await.unwrap()
+}
+
+async fn assert_applies_all_migrations(pool: &AnyPool, backend: &dyn DbBackend) {
+    let applied = migrations::apply(pool, backend, false).await.unwrap();
+    assert_eq!(applied.len(), MIGRATIONS.len());
+    assert_eq!(migrations::current_version(pool, backend).await.unwrap(), MIGRATIONS.last().unwrap().version);
+
+    let status = migrations::status(pool, backend).await.unwrap();
+    assert!(status.iter().all(|s| s.applied_at.is_some()));
+
+    let applied_again = migrations::apply(pool, backend, false).await.unwrap();
+    assert!(applied_again.is_empty());
+}
+
+async fn assert_dry_run_does_not_change_schema(pool: &AnyPool, backend: &dyn DbBackend) {
+    let pending = migrations::apply(pool, backend, true).await.unwrap();
+    assert_eq!(pending.len(), MIGRATIONS.len());
+    assert_eq!(migrations::current_version(pool, backend).await.unwrap(), 0);
+    assert!(migrations::status(pool, backend).await.unwrap().iter().all(|s| s.applied_at.is_none()));
+    assert!(pool.execute("SELECT COUNT(*) FROM user_gift_codes").await.is_err());
+    assert!(pool.execute("SELECT COUNT(*) FROM schema_version").await.is_err());
+}
+
+#[tokio::test]
+async fn applies_all_migrations_to_empty_database() {
+    let pool = memory_pool().await;
+    assert_applies_all_migrations(&pool, &SqliteBackend).await;
+}
+
+#[tokio::test]
+async fn dry_run_does_not_change_schema() {
+    let pool = memory_pool().await;
+    assert_dry_run_does_not_change_schema(&pool, &SqliteBackend).await;
+}
+
+#[tokio::test]
+async fn migrates_legacy_redemptions() {
+    let pool = memory_pool().await;
+    pool.execute("CREATE TABLE user_gift_codes (user_id INTEGER NOT NULL, gift_code_key TEXT NOT NULL, PRIMARY KEY(user_id, gift_code_key))")
+        .await.unwrap();
+    pool.execute("INSERT INTO user_gift_codes (user_id, gift_code_key) VALUES ('123456789012345678', 'ABCDEFGHIJKLMNPQ')")
+        .await.unwrap();
+
+    migrations::apply(&pool, &SqliteBackend, false).await.unwrap();
+
+    let (count,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM user_gift_codes WHERE user_id = $1 AND gift_code_key = $2")
+        .bind(123456789012345678_i64)
+        .bind("ABCDEFGHIJKLMNPQ")
+        .fetch_one(&pool).await.unwrap();
+    assert_eq!(count, 1);
+}
+
+#[tokio::test]
+#[ignore = "needs a Postgres database in TEST_POSTGRES_URL"]
+async fn migrations_on_postgres() {
+    let pool = postgres_pool().await;
+
+    assert_dry_run_does_not_change_schema(&pool, &PostgresBackend).await;
+    assert_applies_all_migrations(&pool, &PostgresBackend).await;
+}
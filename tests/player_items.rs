@@ -0,0 +1,63 @@
+use std::str::FromStr;
+use serde_json::json;
+use unity_discordbot::models::{AccessClass, FileUploadRequest, PlayerFilesPage, PlayerItemsPage, SignedUrlResponse};
+
+#[test]
+fn parses_player_items_page() {
+    let page: PlayerItemsPage = serde_json::from_value(json!({
+        "results": [
+            {
+                "key": "settings",
+                "value": "{\"volume\":3}",
+                "writeLock": "7f1e",
+                "modified": { "date": "2026-03-01T10:00:00Z" },
+                "created": { "date": "2026-01-01T10:00:00Z" },
+            },
+            { "key": "inbox", "value": [1, 2, 3] },
+        ],
+        "links": { "next": "/v1/data/projects/p/environments/e/players/player-1/items?after=inbox" },
+    })).unwrap();
+
+    assert_eq!(page.results.len(), 2);
+    assert_eq!(page.results[0].size(), 12);
+    assert_eq!(page.results[0].modified.as_ref().unwrap().date, "2026-03-01T10:00:00Z");
+    assert_eq!(page.results[1].size(), 7);
+    assert!(page.results[1].modified.is_none());
+    assert!(page.links.next.is_some());
+
+    let last_page: PlayerItemsPage = serde_json::from_value(json!({ "results": [] })).unwrap();
+    assert!(last_page.links.next.is_none());
+}
+
+#[test]
+fn maps_access_classes_to_item_paths() {
+    assert_eq!(AccessClass::from_str("Protected").unwrap(), AccessClass::Protected);
+    assert_eq!(AccessClass::Default.items_path(), "items");
+    assert_eq!(AccessClass::Public.items_path(), "public/items");
+    assert_eq!(AccessClass::Protected.items_path(), "protected/items");
+    assert_eq!(AccessClass::Private.items_path(), "private/items");
+    assert!(AccessClass::from_str("secret").is_err());
+}
+
+#[test]
+fn parses_player_files_and_signed_urls() {
+    let page: PlayerFilesPage = serde_json::from_value(json!({
+        "results": [
+            { "key": "world.bin", "size": 2048, "contentType": "application/octet-stream", "modified": { "date": "2026-03-01T10:00:00Z" } },
+        ],
+        "links": { "next": null },
+    })).unwrap();
+    assert_eq!(page.results[0].size, 2048);
+    assert_eq!(page.results[0].content_type.as_deref(), Some("application/octet-stream"));
+    assert!(page.links.next.is_none());
+
+    let signed_url: SignedUrlResponse = serde_json::from_value(json!({ "signedUrl": "https://storage.example/world.bin?sig=1" })).unwrap();
+    assert_eq!(signed_url.signed_url, "https://storage.example/world.bin?sig=1");
+
+    let request = FileUploadRequest { content_type: "application/json".to_string(), content_length: 2, content_md5: "mZFLkyvTelC5g8XnyQrpOw==".to_string() };
+    assert_eq!(serde_json::to_value(&request).unwrap(), json!({
+        "contentType": "application/json",
+        "contentLength": 2,
+        "contentMd5": "mZFLkyvTelC5g8XnyQrpOw==",
+    }));
+}
@@ -0,0 +1,42 @@
+use serde_json::json;
+use unity_discordbot::config::{PlayerSummaryConfig, PlayerSummaryField};
+use unity_discordbot::player_summary::get_player_summary_embed;
+
+#[test]
+fn summarises_default_fields() {
+    let save_data = json!({
+        "playerProgressData": { "saveCount": 3, "level": 12, "xp": 450 },
+        "playerInventoryData": { "currencies": { "gold": 100, "gems": 5 } },
+    });
+    let embed = serde_json::to_value(get_player_summary_embed("player1", &save_data, &PlayerSummaryConfig::default())).unwrap();
+
+    let fields = embed["fields"].as_array().unwrap();
+    assert_eq!(fields[1]["name"], "Level");
+    assert_eq!(fields[1]["value"], "12");
+    assert_eq!(fields[3]["value"], "gems: 5\ngold: 100");
+    assert_eq!(fields.last().unwrap()["name"], "Subscriptions");
+}
+
+#[test]
+fn moves_fields_over_the_limit_to_the_description() {
+    let config = PlayerSummaryConfig {
+        fields: (0..30).map(|i| PlayerSummaryField { label: format!("Field {}", i), pointer: format!("/f{}", i) }).collect(),
+        ..PlayerSummaryConfig::default()
+    };
+    let embed = serde_json::to_value(get_player_summary_embed("player1", &json!({ "f29": "last" }), &config)).unwrap();
+
+    assert_eq!(embed["fields"].as_array().unwrap().len(), 25);
+    let description = embed["description"].as_str().unwrap();
+    assert!(description.contains("**Field 29**: last"));
+    assert!(description.ends_with("**Subscriptions**: None"));
+
+    // A big inventory has to fit Discord's 1024 characters per field and 4096 in the description.
+    let currencies: serde_json::Map<String, serde_json::Value> = (0..500).map(|i| (format!("currency{}", i), json!(i))).collect();
+    let save_data = json!({ "f0": currencies, "f29": currencies });
+    let embed = serde_json::to_value(get_player_summary_embed("player1", &save_data, &config)).unwrap();
+
+    let fields = embed["fields"].as_array().unwrap();
+    assert!(fields.iter().all(|field| (1..=1024).contains(&field["value"].as_str().unwrap().chars().count())));
+    assert!(fields[0]["value"].as_str().unwrap().ends_with('…'));
+    assert!(embed["description"].as_str().unwrap().chars().count() <= 4096);
+}
@@ -0,0 +1,83 @@
+use std::sync::Arc;
+use chrono::Utc;
+use serde_json::json;
+use unity_discordbot::db::Db;
+use unity_discordbot::memory_cloud_save::MemoryCloudSave;
+use unity_discordbot::models::{GiftCode, GiftCodeRedemption};
+use unity_discordbot::reconcile::{get_reconcile_report, reconcile_gift_codes, repair_gift_code, GiftCodeReconciliation, ReconcileRepair};
+use unity_discordbot::unity_service::UnityService;
+
+fn reconciliation(cloud_amount: u32, redemptions: u32, revoked_without_return: u32) -> GiftCodeReconciliation {
+    GiftCodeReconciliation {
+        gift_code_key: "ABCDEFGHIJKLMNPQ".to_string(),
+        initial_amount: Some(10),
+        cloud_amount,
+        redemptions,
+        revoked_without_return,
+    }
+}
+
+#[test]
+fn revocations_without_return_are_not_drift() {
+    // 3 redeemed, 1 of them revoked without returning the unit: 2 rows left and 7 in Cloud Save.
+    let revoked = reconciliation(7, 2, 1);
+    assert_eq!(revoked.expected_cloud_amount(), Some(7));
+    assert!(!revoked.has_drift());
+
+    let drifted = reconciliation(8, 2, 1);
+    assert!(drifted.has_drift());
+    assert!(get_reconcile_report(&[revoked, drifted]).starts_with("Drift found in 1 of 2 gift codes"));
+}
+
+fn redemption(user_id: u64) -> GiftCodeRedemption {
+    GiftCodeRedemption {
+        gift_code_key: "ABCDEFGHIJKLMNPQ".to_string(),
+        user_id,
+        username: "player".to_string(),
+        guild_id: None,
+        channel_id: 1234567890123456789,
+        remaining_amount: 0,
+        redeemed_at: Utc::now().to_rfc3339(),
+    }
+}
+
+#[tokio::test]
+async fn cloud_repair_skips_codes_redeemed_since_the_reconciliation() {
+    std::env::set_var("UNITY_SAVE_DATA_KEY", "saveData");
+    std::env::set_var("SAVE_DATA_SCHEMA_PATH", "");
+    let db = Db::connect("sqlite::memory:").await.unwrap();
+    db.migrate(false).await.unwrap();
+    let db = Arc::new(db);
+    let unity_service = UnityService::with_cloud_save("production", Box::new(MemoryCloudSave::new()), db.clone()).unwrap();
+
+    let key = "ABCDEFGHIJKLMNPQ".to_string();
+    let mut gift_code: GiftCode = serde_json::from_value(json!({
+        "title": "Launch",
+        "subtitle": "Thanks for playing",
+        "amount": 8,
+        "duration": 7,
+        "expiredAt": "2030-01-01T00:00:00Z",
+        "rewards": { "currencyRewards": [], "itemRewards": [], "xpReward": 100 },
+        "channelId": 1,
+        "messageId": "2",
+        "buttonId": "launch",
+    })).unwrap();
+    unity_service.save_gift_code(&key, &gift_code).await.unwrap();
+    db.record_gift_code_in_db(&key, 10).await.unwrap();
+    db.redeem_gift_code_in_db(&redemption(1)).await.unwrap();
+
+    let reconciliations = reconcile_gift_codes(&unity_service, &db).await.unwrap();
+    assert!(reconciliations[0].has_drift());
+
+    // A player redeems between the reconciliation and the repair.
+    gift_code.amount = 7;
+    unity_service.save_gift_code(&key, &gift_code).await.unwrap();
+    db.redeem_gift_code_in_db(&redemption(2)).await.unwrap();
+
+    assert!(!repair_gift_code(&unity_service, &db, &reconciliations[0], &ReconcileRepair::Cloud).await.unwrap());
+    assert_eq!(unity_service.get_gift_code(key.clone()).await.unwrap().amount, 7);
+
+    let reconciliations = reconcile_gift_codes(&unity_service, &db).await.unwrap();
+    assert!(repair_gift_code(&unity_service, &db, &reconciliations[0], &ReconcileRepair::Cloud).await.unwrap());
+    assert_eq!(unity_service.get_gift_code(key.clone()).await.unwrap().amount, 8);
+}
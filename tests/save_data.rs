@@ -0,0 +1,121 @@
+use chrono::{Duration, TimeZone, Utc};
+use serde_json::json;
+use unity_discordbot::config::GrantRewardsConfig;
+use unity_discordbot::models::GiftCodeReward;
+use unity_discordbot::save_data::{apply_grant, apply_save_data_patch, get_subscription_expiry, increase_save_count, remove_value_at_pointer, set_subscription_expiry, set_value_at_pointer, update_subscription};
+
+#[test]
+fn sets_and_removes_values_by_pointer() {
+    let mut save_data = json!({
+        "playerProgressData": { "saveCount": 7 },
+        "inventory": [1, 2],
+        "a/b": {},
+    });
+
+    assert_eq!(set_value_at_pointer(&mut save_data, "/playerProgressData/level", json!(5)).unwrap(), None);
+    assert_eq!(set_value_at_pointer(&mut save_data, "/inventory/0", json!(9)).unwrap(), Some(json!(1)));
+    assert_eq!(set_value_at_pointer(&mut save_data, "/inventory/-", json!(3)).unwrap(), None);
+    assert_eq!(set_value_at_pointer(&mut save_data, "/a~1b/c", json!(true)).unwrap(), None);
+    assert_eq!(remove_value_at_pointer(&mut save_data, "/inventory/1").unwrap(), json!(2));
+    increase_save_count(&mut save_data, 2).unwrap();
+
+    assert_eq!(save_data, json!({
+        "playerProgressData": { "saveCount": 9, "level": 5 },
+        "inventory": [9, 3],
+        "a/b": { "c": true },
+    }));
+}
+
+#[test]
+fn rejects_invalid_pointers() {
+    let mut save_data = json!({ "inventory": [1], "count": 1 });
+
+    assert!(set_value_at_pointer(&mut save_data, "inventory", json!(1)).is_err());
+    assert!(set_value_at_pointer(&mut save_data, "/missing/field", json!(1)).is_err());
+    assert!(set_value_at_pointer(&mut save_data, "/inventory/5", json!(1)).is_err());
+    assert!(set_value_at_pointer(&mut save_data, "/count/field", json!(1)).is_err());
+    assert!(remove_value_at_pointer(&mut save_data, "/inventory/1").is_err());
+    assert!(remove_value_at_pointer(&mut save_data, "/missing").is_err());
+    assert!(increase_save_count(&mut save_data, 1).is_err());
+}
+
+#[test]
+fn applies_json_patch_atomically() {
+    let save_data = json!({ "playerProgressData": { "saveCount": 1, "level": 3 }, "items": [] });
+
+    let patched = apply_save_data_patch(&save_data, json!([
+        { "op": "test", "path": "/playerProgressData/level", "value": 3 },
+        { "op": "replace", "path": "/playerProgressData/level", "value": 4 },
+        { "op": "add", "path": "/items/-", "value": { "id": 1 } },
+        { "op": "copy", "from": "/items/0", "path": "/items/-" },
+        { "op": "move", "from": "/playerProgressData/level", "path": "/level" },
+    ])).unwrap();
+    assert_eq!(patched, json!({ "playerProgressData": { "saveCount": 1 }, "items": [{ "id": 1 }, { "id": 1 }], "level": 4 }));
+
+    let failed_precondition = apply_save_data_patch(&save_data, json!([
+        { "op": "remove", "path": "/items" },
+        { "op": "test", "path": "/playerProgressData/level", "value": 99 },
+    ]));
+    assert!(failed_precondition.is_err());
+    assert!(apply_save_data_patch(&save_data, json!([{ "op": "explode", "path": "/" }])).is_err());
+}
+
+#[test]
+fn reads_and_writes_subscription_expiry() {
+    let mut save_data = json!({
+        "playerAccountData": { "shopData": { "shopSubscriptionData": {
+            "premium": { "expiresAt": "2026-01-01T00:00:00+00:00" },
+        } } },
+    });
+
+    let expires_at = Utc.with_ymd_and_hms(2026, 1, 1, 0, 0, 0).unwrap();
+    assert_eq!(get_subscription_expiry(&save_data, "premium").unwrap(), Some(expires_at));
+    assert_eq!(get_subscription_expiry(&save_data, "vip").unwrap(), None);
+
+    let extended = Utc.with_ymd_and_hms(2026, 2, 1, 0, 0, 0).unwrap();
+    set_subscription_expiry(&mut save_data, "vip", extended).unwrap();
+    assert_eq!(get_subscription_expiry(&save_data, "vip").unwrap(), Some(extended));
+    assert_eq!(get_subscription_expiry(&save_data, "premium").unwrap(), Some(expires_at));
+
+    assert!(set_subscription_expiry(&mut json!({}), "vip", extended).is_err());
+}
+
+#[test]
+fn grants_rewards_at_configured_pointers() {
+    let mut save_data = json!({
+        "playerProgressData": { "saveCount": 3, "xp": 100 },
+        "playerInventoryData": { "currencies": { "1": 50 }, "items": [] },
+    });
+    let rewards: GiftCodeReward = serde_json::from_value(json!({
+        "currencyRewards": [
+            { "name": "Gold", "currencyType": 1, "currencyAmount": 25 },
+            { "name": "Gems", "currencyType": 2, "currencyAmount": 5 },
+        ],
+        "itemRewards": [
+            { "name": "Sword", "itemId": 7, "itemGrade": 2, "upgradeLevel": 0, "itemRefinementQuality": 1 },
+        ],
+        "xpReward": 40,
+    })).unwrap();
+
+    apply_grant(&mut save_data, &rewards, &GrantRewardsConfig::default()).unwrap();
+    assert_eq!(save_data["playerInventoryData"]["currencies"], json!({ "1": 75, "2": 5 }));
+    assert_eq!(save_data["playerInventoryData"]["items"], json!([
+        { "itemId": 7, "itemGrade": 2, "upgradeLevel": 0, "itemRefinementQuality": 1 },
+    ]));
+    assert_eq!(save_data["playerProgressData"]["xp"], json!(140));
+
+    assert!(apply_grant(&mut json!({}), &rewards, &GrantRewardsConfig::default()).is_err());
+}
+
+#[test]
+fn updates_subscription_from_now() {
+    let mut save_data = json!({
+        "playerProgressData": { "saveCount": 3 },
+        "playerAccountData": { "shopData": { "shopSubscriptionData": {} } },
+    });
+
+    update_subscription(&mut save_data, "premium", 30, 1).unwrap();
+    let expires_at = get_subscription_expiry(&save_data, "premium").unwrap().unwrap();
+    assert!((expires_at - (Utc::now() + Duration::days(30))).num_seconds().abs() < 5);
+    assert_eq!(save_data["playerProgressData"]["saveCount"], json!(4));
+}
@@ -0,0 +1,91 @@
+mod common;
+
+use std::sync::Arc;
+use base64::{encode_config, URL_SAFE_NO_PAD};
+use chrono::{Duration, Utc};
+use serde_json::json;
+use common::{start_stub, StubLog};
+use unity_discordbot::config::{UnityEnvironmentConfig, UnityRequestConfig};
+use unity_discordbot::db::Db;
+use unity_discordbot::unity_auth::UnityTokenProvider;
+use unity_discordbot::unity_service::UnityService;
+
+fn access_token(expires_in: Duration) -> String {
+    let claims = json!({ "exp": (Utc::now() + expires_in).timestamp() });
+    format!("header.{}.signature", encode_config(claims.to_string(), URL_SAFE_NO_PAD))
+}
+
+fn token_exchanges(log: &StubLog) -> usize {
+    log.lock().unwrap().iter().filter(|request| request.path.starts_with("/auth/v1/token-exchange")).count()
+}
+
+#[tokio::test]
+async fn caches_token_until_invalidated() {
+    let (base_url, log) = start_stub(|_, log| {
+        let token = format!("{}-{}", access_token(Duration::hours(1)), log.len());
+        (200, json!({ "accessToken": token }).to_string())
+    }).await;
+    let provider = UnityTokenProvider::new(reqwest::Client::new(), &base_url, "project", "environment", "key", "secret");
+
+    let first = provider.get_auth_header().await.unwrap();
+    assert_eq!(provider.get_auth_header().await.unwrap(), first);
+    assert!(first.starts_with("Bearer "));
+    assert_eq!(token_exchanges(&log), 1);
+
+    let request = log.lock().unwrap()[0].clone();
+    assert_eq!(request.method, "POST");
+    assert_eq!(request.path, "/auth/v1/token-exchange?projectId=project&environmentId=environment");
+    assert_eq!(request.authorization, "Basic a2V5OnNlY3JldA==");
+
+    provider.invalidate().await;
+    assert_ne!(provider.get_auth_header().await.unwrap(), first);
+    assert_eq!(token_exchanges(&log), 2);
+}
+
+#[tokio::test]
+async fn refreshes_token_close_to_expiry() {
+    let (base_url, log) = start_stub(|_, _| {
+        (200, json!({ "accessToken": access_token(Duration::seconds(60)) }).to_string())
+    }).await;
+    let provider = UnityTokenProvider::new(reqwest::Client::new(), &base_url, "project", "environment", "key", "secret");
+
+    provider.get_auth_header().await.unwrap();
+    provider.get_auth_header().await.unwrap();
+    assert_eq!(token_exchanges(&log), 2);
+}
+
+#[tokio::test]
+async fn retries_once_on_unauthorized() {
+    let (base_url, log) = start_stub(|request, log| {
+        if request.path.starts_with("/auth/v1/token-exchange") {
+            let exchanges = log.iter().filter(|r| r.path.starts_with("/auth/v1/token-exchange")).count();
+            return (200, json!({ "accessToken": format!("token-{}", exchanges) }).to_string());
+        }
+        match request.authorization.as_str() {
+            "Bearer token-0" => (401, json!({ "title": "Unauthorized" }).to_string()),
+            _ => (200, json!({ "results": [] }).to_string()),
+        }
+    }).await;
+
+    std::env::set_var("UNITY_API_BASE_URL", &base_url);
+    std::env::set_var("UNITY_SAVE_DATA_KEY", "saveData");
+    std::env::set_var("SAVE_DATA_SCHEMA_PATH", "");
+    std::env::set_var("UNITY_REQUEST", serde_json::to_string(&UnityRequestConfig::default()).unwrap());
+    let db = Arc::new(Db::connect("sqlite::memory:").await.unwrap());
+    let unity_service = UnityService::new("production", &UnityEnvironmentConfig {
+        unity_project_id: Some("project".to_string()),
+        unity_environment_id: Some("environment".to_string()),
+        unity_key_id: Some("key".to_string()),
+        unity_secret_key: Some("secret".to_string()),
+        cloud_save: None,
+    }, db).unwrap();
+
+    assert_eq!(unity_service.get_gift_code_count().await.unwrap(), 0);
+    assert_eq!(token_exchanges(&log), 2);
+
+    let requests = log.lock().unwrap().clone();
+    let gift_code_requests: Vec<_> = requests.iter().filter(|r| r.path.contains("/gift_codes/items")).collect();
+    assert_eq!(gift_code_requests.len(), 2);
+    assert_eq!(gift_code_requests[0].path, "/cloud-save/v1/data/projects/project/environments/environment/custom/gift_codes/items");
+    assert_eq!(gift_code_requests[1].authorization, "Bearer token-1");
+}
@@ -0,0 +1,90 @@
+mod common;
+
+use std::sync::Arc;
+use serde_json::json;
+use common::start_stub;
+use unity_discordbot::config::{UnityEnvironmentConfig, UnityRequestConfig};
+use unity_discordbot::db::Db;
+use unity_discordbot::unity_error::{UnityError, UnityErrorKind};
+use unity_discordbot::unity_service::UnityService;
+
+#[test]
+fn parses_problem_json() {
+    let body = json!({
+        "type": "https://services.docs.unity.com/docs/errors/#10009",
+        "title": "Conflict",
+        "status": 409,
+        "code": 10009,
+        "detail": "The write lock does not match",
+    }).to_string();
+    let error = UnityError::from_body("Failed to save gift code", 409, "https://example.com/items", &body);
+
+    assert_eq!(error.kind, UnityErrorKind::Conflict);
+    assert_eq!(error.status, 409);
+    assert_eq!(error.code, Some(10009));
+    assert_eq!(error.title.as_deref(), Some("Conflict"));
+    assert_eq!(error.detail.as_deref(), Some("The write lock does not match"));
+    assert!(!error.is_retryable());
+    assert_eq!(
+        error.to_string(),
+        "Failed to save gift code: 409 Conflict: The write lock does not match (code 10009), request_url: https://example.com/items"
+    );
+}
+
+#[test]
+fn keeps_plain_body_as_detail() {
+    let error = UnityError::from_body("Failed to get gift codes", 502, "https://example.com/items", "Bad Gateway\n");
+
+    assert_eq!(error.kind, UnityErrorKind::Server);
+    assert_eq!(error.code, None);
+    assert_eq!(error.title, None);
+    assert_eq!(error.detail.as_deref(), Some("Bad Gateway"));
+    assert!(error.is_retryable());
+}
+
+#[test]
+fn maps_status_to_kind() {
+    assert_eq!(UnityErrorKind::from_status(400), UnityErrorKind::BadRequest);
+    assert_eq!(UnityErrorKind::from_status(401), UnityErrorKind::Unauthorized);
+    assert_eq!(UnityErrorKind::from_status(403), UnityErrorKind::Forbidden);
+    assert_eq!(UnityErrorKind::from_status(404), UnityErrorKind::NotFound);
+    assert_eq!(UnityErrorKind::from_status(429), UnityErrorKind::RateLimited);
+    assert_eq!(UnityErrorKind::from_status(503), UnityErrorKind::Server);
+    assert_eq!(UnityErrorKind::from_status(418), UnityErrorKind::Other);
+}
+
+#[tokio::test]
+async fn unity_service_returns_unity_errors() {
+    let (base_url, _) = start_stub(|request, _| {
+        if request.path.starts_with("/auth/v1/token-exchange") {
+            return (200, json!({ "accessToken": "token" }).to_string());
+        }
+        match request.method.as_str() {
+            "GET" if request.path.contains("keys=missing") => (200, json!({ "results": [] }).to_string()),
+            _ => (403, json!({ "title": "Forbidden", "code": 51, "detail": "Access denied" }).to_string()),
+        }
+    }).await;
+
+    std::env::set_var("UNITY_API_BASE_URL", &base_url);
+    std::env::set_var("UNITY_SAVE_DATA_KEY", "saveData");
+    std::env::set_var("SAVE_DATA_SCHEMA_PATH", "");
+    std::env::set_var("UNITY_REQUEST", serde_json::to_string(&UnityRequestConfig::default()).unwrap());
+    let db = Arc::new(Db::connect("sqlite::memory:").await.unwrap());
+    let unity_service = UnityService::new("production", &UnityEnvironmentConfig {
+        unity_project_id: Some("project".to_string()),
+        unity_environment_id: Some("environment".to_string()),
+        unity_key_id: Some("key".to_string()),
+        unity_secret_key: Some("secret".to_string()),
+        cloud_save: None,
+    }, db).unwrap();
+
+    let error = unity_service.get_gift_code("missing".to_string()).await.unwrap_err();
+    let error = error.downcast_ref::<UnityError>().unwrap();
+    assert_eq!(error.kind, UnityErrorKind::NotFound);
+
+    let error = unity_service.get_all_gift_codes().await.unwrap_err();
+    let error = error.downcast_ref::<UnityError>().unwrap();
+    assert_eq!(error.kind, UnityErrorKind::Forbidden);
+    assert_eq!(error.code, Some(51));
+    assert!(error.url.ends_with("/custom/gift_codes/items"));
+}
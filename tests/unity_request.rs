@@ -0,0 +1,118 @@
+mod common;
+
+use std::time::{Duration, Instant};
+use serde_json::json;
+use common::{start_stub, start_stub_with_retry_after, StubRequest};
+use unity_discordbot::config::UnityRequestConfig;
+use unity_discordbot::unity_auth::UnityTokenProvider;
+use unity_discordbot::unity_request::{backoff_delay, parse_retry_after, TokenBucket, UnityRequestExecutor};
+
+fn fast_config() -> UnityRequestConfig {
+    UnityRequestConfig {
+        initial_backoff_millis: 1,
+        max_backoff_millis: 5,
+        rate_limit_requests: 1000,
+        ..UnityRequestConfig::default()
+    }
+}
+
+fn executor(base_url: &str) -> UnityRequestExecutor {
+    let client = reqwest::Client::new();
+    let auth = UnityTokenProvider::new(client.clone(), base_url, "project", "environment", "key", "secret");
+    UnityRequestExecutor::new(client, auth, fast_config())
+}
+
+fn api_requests(log: &[StubRequest]) -> usize {
+    log.iter().filter(|request| !request.path.starts_with("/auth/")).count()
+}
+
+// Fails the first `failures` API calls with `status`, token exchanges always succeed.
+fn failing(status: u16, failures: usize) -> impl Fn(&StubRequest, &[StubRequest]) -> (u16, String) {
+    move |request, log| {
+        if request.path.starts_with("/auth/") {
+            return (200, json!({ "accessToken": "token" }).to_string());
+        }
+        if api_requests(log) < failures {
+            (status, json!({ "title": "Failure" }).to_string())
+        } else {
+            (200, json!({ "results": [] }).to_string())
+        }
+    }
+}
+
+#[test]
+fn backoff_stays_within_exponential_ceiling() {
+    let initial = Duration::from_millis(100);
+    let max = Duration::from_millis(1000);
+    for _ in 0..50 {
+        assert!(backoff_delay(0, initial, max) <= Duration::from_millis(100));
+        assert!(backoff_delay(2, initial, max) <= Duration::from_millis(400));
+        assert!(backoff_delay(10, initial, max) <= max);
+    }
+}
+
+#[test]
+fn parses_retry_after() {
+    assert_eq!(parse_retry_after("3"), Some(Duration::from_secs(3)));
+    assert_eq!(parse_retry_after("Wed, 21 Oct 2015 07:28:00 GMT"), Some(Duration::ZERO));
+    assert!(parse_retry_after("soon").is_none());
+}
+
+#[tokio::test]
+async fn token_bucket_waits_once_empty() {
+    let bucket = TokenBucket::new(2, Duration::from_millis(200));
+    let started = Instant::now();
+    bucket.acquire().await;
+    bucket.acquire().await;
+    assert!(started.elapsed() < Duration::from_millis(50));
+
+    bucket.acquire().await;
+    assert!(started.elapsed() >= Duration::from_millis(80));
+}
+
+#[tokio::test]
+async fn retries_rate_limited_requests() {
+    let (base_url, log) = start_stub(failing(429, 2)).await;
+    let client = reqwest::Client::new();
+
+    let response = executor(&base_url).send(client.post(format!("{}/items", base_url))).await.unwrap();
+    assert!(response.status().is_success());
+    assert_eq!(api_requests(&log.lock().unwrap()), 3);
+}
+
+#[tokio::test]
+async fn returns_rate_limited_response_when_retry_after_exceeds_backoff_cap() {
+    let (base_url, log) = start_stub_with_retry_after(failing(429, 1), 60).await;
+    let client = reqwest::Client::new();
+
+    let started = Instant::now();
+    let response = executor(&base_url).send(client.get(format!("{}/items", base_url))).await.unwrap();
+    assert_eq!(response.status().as_u16(), 429);
+    assert_eq!(api_requests(&log.lock().unwrap()), 1);
+    assert!(started.elapsed() < Duration::from_secs(5));
+}
+
+#[tokio::test]
+async fn retries_server_errors_only_when_idempotent() {
+    let (base_url, log) = start_stub(failing(503, 1)).await;
+    let client = reqwest::Client::new();
+    let executor = executor(&base_url);
+
+    let response = executor.send(client.post(format!("{}/items", base_url))).await.unwrap();
+    assert_eq!(response.status().as_u16(), 503);
+    assert_eq!(api_requests(&log.lock().unwrap()), 1);
+
+    let response = executor.send(client.get(format!("{}/items", base_url))).await.unwrap();
+    assert!(response.status().is_success());
+    assert_eq!(api_requests(&log.lock().unwrap()), 2);
+}
+
+#[tokio::test]
+async fn gives_up_after_max_retries() {
+    let (base_url, log) = start_stub(failing(500, usize::MAX)).await;
+    let client = reqwest::Client::new();
+
+    let response = executor(&base_url).send(client.get(format!("{}/items", base_url))).await.unwrap();
+    assert_eq!(response.status().as_u16(), 500);
+    assert_eq!(api_requests(&log.lock().unwrap()), 1 + fast_config().max_retries as usize);
+}